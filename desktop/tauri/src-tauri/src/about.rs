@@ -0,0 +1,341 @@
+use crate::events::{self, EventKind};
+use crate::http;
+use crate::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const ABOUT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Server major versions whose health, settings and GraphQL endpoints the launcher uses as
+/// written.
+const SUPPORTED_SERVER_MAJORS: RangeInclusive<u64> = 1..=2;
+
+/// The version warning `about` last saw, so asking again about the same server does not
+/// record the same event every time.
+static LAST_VERSION_WARNING: Mutex<Option<String>> = Mutex::new(None);
+
+/// Build information for the launcher and, when reachable, the server it is connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AboutInfo {
+    pub launcher_version: String,
+    pub base_url: Option<String>,
+    pub server: Option<ServerAbout>,
+    /// Set when the server's major version is outside what this launcher was built for.
+    pub version_warning: Option<String>,
+}
+
+/// What the server reports at its about endpoint. Every field is optional because the
+/// schema has changed between server releases (e.g. `revision` is deprecated).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerAbout {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub revision: Option<String>,
+    pub build_type: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub build_time: Option<u64>,
+    pub github: Option<String>,
+    pub discord: Option<String>,
+}
+
+/// What a single request to a server the user typed in found, without connecting to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResult {
+    /// The URL as `connect_to` would use it; `None` when it is not a valid server URL.
+    pub base_url: Option<String>,
+    /// The server answered its about endpoint, so connecting to it would succeed.
+    pub reachable: bool,
+    /// `None` when nothing answered at all.
+    pub status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub server_version: Option<String>,
+    /// The server (or a proxy in front of it) wants credentials the launcher did not send.
+    pub auth_required: bool,
+}
+
+impl fmt::Display for AboutInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Launcher {}", self.launcher_version)?;
+
+        let Some(server) = &self.server else {
+            return match &self.base_url {
+                Some(base_url) => write!(f, "Server at {base_url} did not answer"),
+                None => write!(f, "Not connected to a server"),
+            };
+        };
+
+        write!(
+            f,
+            "{} {}",
+            server.name.as_deref().unwrap_or("Server"),
+            server.version.as_deref().unwrap_or("(unknown version)")
+        )?;
+        if let Some(revision) = &server.revision {
+            write!(f, " ({revision})")?;
+        }
+        if let Some(build_type) = &server.build_type {
+            write!(f, "\nBuild type: {build_type}")?;
+        }
+        if let Some(build_time) = server.build_time {
+            write!(f, "\nBuilt: {}", format_utc(build_time))?;
+        }
+        if let Some(base_url) = &self.base_url {
+            write!(f, "\nServer URL: {base_url}")?;
+        }
+        if let Some(warning) = &self.version_warning {
+            write!(f, "\n\n{warning}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn about() -> AboutInfo {
+    let base_url = launcher::active_base_url();
+    let server = base_url.as_deref().and_then(fetch_server_about);
+    let version_warning = server
+        .as_ref()
+        .and_then(|server| server.version.as_deref())
+        .and_then(version_warning);
+    let changed = warning_changed(&LAST_VERSION_WARNING, version_warning.as_deref());
+    if let Some(warning) = version_warning.as_ref().filter(|_| changed) {
+        eprintln!("{warning}");
+        events::record(EventKind::UnsupportedVersion, warning.clone());
+    }
+
+    AboutInfo {
+        launcher_version: env!("CARGO_PKG_VERSION").to_string(),
+        base_url,
+        server,
+        version_warning,
+    }
+}
+
+/// Remembers `warning` in `last`, returning whether it differs from what was there.
+fn warning_changed(last: &Mutex<Option<String>>, warning: Option<&str>) -> bool {
+    let mut last = last.lock().expect("version warning mutex poisoned");
+    if last.as_deref() == warning {
+        return false;
+    }
+    *last = warning.map(str::to_string);
+    true
+}
+
+/// Checks `url` for the manual-connect form: one request to the about endpoint, nothing
+/// recorded and the active connection left alone.
+pub fn probe_url(url: &str) -> ProbeResult {
+    let Some(base_url) = launcher::normalize_base_url(url)
+        .filter(|base_url| base_url.starts_with("http://") || base_url.starts_with("https://"))
+    else {
+        return ProbeResult::default();
+    };
+
+    let started = Instant::now();
+    let response = http::get(
+        &format!("{base_url}{}", launcher::HEALTH_ENDPOINT),
+        ABOUT_TIMEOUT,
+    );
+    let latency = started.elapsed();
+    let Some(response) = response else {
+        return ProbeResult {
+            base_url: Some(base_url),
+            ..ProbeResult::default()
+        };
+    };
+
+    let reachable = (200..=299).contains(&response.status);
+    ProbeResult {
+        base_url: Some(base_url),
+        reachable,
+        status: Some(response.status),
+        latency_ms: Some(events::millis(latency)),
+        server_version: reachable
+            .then(|| parse_server_about(&response.body)?.version)
+            .flatten(),
+        auth_required: matches!(response.status, 401 | 403),
+    }
+}
+
+/// A caution for servers outside [`SUPPORTED_SERVER_MAJORS`]. Nothing is refused: most of
+/// the API is stable across releases. Versions that cannot be parsed are not flagged.
+fn version_warning(version: &str) -> Option<String> {
+    let major: u64 = version
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split(['.', '-'])
+        .next()?
+        .parse()
+        .ok()?;
+    if SUPPORTED_SERVER_MAJORS.contains(&major) {
+        return None;
+    }
+
+    Some(format!(
+        "Server {version} is a major version this launcher was not built for (supports \
+         v{}.x to v{}.x); some features may not work.",
+        SUPPORTED_SERVER_MAJORS.start(),
+        SUPPORTED_SERVER_MAJORS.end()
+    ))
+}
+
+fn fetch_server_about(base_url: &str) -> Option<ServerAbout> {
+    let about_url = format!(
+        "{}{}",
+        base_url.trim_end_matches('/'),
+        launcher::HEALTH_ENDPOINT
+    );
+    let response = http::get(&about_url, ABOUT_TIMEOUT)?;
+    if response.status != 200 {
+        return None;
+    }
+
+    parse_server_about(&response.body)
+}
+
+fn parse_server_about(body: &[u8]) -> Option<ServerAbout> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let object = value.as_object()?;
+    let text = |key: &str| match object.get(key)? {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+
+    Some(ServerAbout {
+        name: text("name"),
+        version: text("version"),
+        revision: text("revision"),
+        build_type: text("buildType"),
+        build_time: object.get("buildTime").and_then(build_time),
+        github: text("github"),
+        discord: text("discord"),
+    })
+}
+
+/// Older servers sent the build time as a string, and some builds in milliseconds.
+fn build_time(value: &Value) -> Option<u64> {
+    let raw = match value {
+        Value::Number(number) => number.as_u64()?,
+        Value::String(text) => text.trim().parse().ok()?,
+        _ => return None,
+    };
+
+    Some(if raw > 100_000_000_000 {
+        raw / 1000
+    } else {
+        raw
+    })
+}
+
+/// `YYYY-MM-DD HH:MM UTC`, using the days-from-civil algorithm to avoid a date crate.
+fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let minutes_of_day = secs % 86_400 / 60;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        minutes_of_day / 60,
+        minutes_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    #[test]
+    fn parses_current_about_response() {
+        let about = parse_server_about(
+            br#"{"name":"Suwayomi-Server","version":"v2.0.1727","revision":"r1727",
+                "buildType":"Stable","buildTime":1735689600,
+                "github":"https://github.com/Suwayomi/Suwayomi-Server","discord":""}"#,
+        )
+        .expect("about");
+
+        assert_eq!(about.version.as_deref(), Some("v2.0.1727"));
+        assert_eq!(about.build_type.as_deref(), Some("Stable"));
+        assert_eq!(about.build_time, Some(1_735_689_600));
+        assert_eq!(about.discord, None);
+    }
+
+    #[test]
+    fn unsupported_major_versions_are_flagged() {
+        assert_eq!(version_warning("v2.0.1727"), None);
+        assert_eq!(version_warning("v1.1.1-preview"), None);
+        assert_eq!(version_warning("r1727"), None);
+
+        let warning = version_warning("v3.0.0").expect("newer major");
+        assert!(
+            warning.contains("v3.0.0") && warning.contains("v1.x to v2.x"),
+            "{warning}"
+        );
+        assert!(version_warning("v0.7.0").is_some());
+    }
+
+    #[test]
+    fn a_version_warning_is_only_new_once() {
+        let last = Mutex::new(None);
+        assert!(warning_changed(&last, Some("v3 is too new")));
+        assert!(!warning_changed(&last, Some("v3 is too new")));
+        assert!(warning_changed(&last, None));
+        assert!(!warning_changed(&last, None));
+        assert!(warning_changed(&last, Some("v3 is too new")));
+    }
+
+    #[test]
+    fn build_time_is_shown_as_a_utc_date() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_utc(1_735_689_600), "2025-01-01 00:00 UTC");
+        assert_eq!(format_utc(1_709_210_096), "2024-02-29 12:34 UTC");
+    }
+
+    #[test]
+    fn tolerates_older_and_partial_schemas() {
+        let about = parse_server_about(br#"{"version":"v0.7.0","buildTime":"1735689600000"}"#)
+            .expect("about");
+
+        assert_eq!(about.version.as_deref(), Some("v0.7.0"));
+        assert_eq!(about.build_time, Some(1_735_689_600));
+        assert_eq!(about.name, None);
+        assert!(parse_server_about(b"[]").is_none());
+    }
+
+    #[test]
+    fn probing_a_url_reports_what_answered_there() {
+        let server = serve(1, |_| {
+            respond(200, r#"{"name":"Suwayomi-Server","version":"v2.1.1867"}"#)
+        });
+        let found = probe_url(&format!("{server}/"));
+        assert_eq!(found.base_url.as_deref(), Some(server.as_str()));
+        assert!(found.reachable);
+        assert_eq!(found.status, Some(200));
+        assert!(found.latency_ms.is_some());
+        assert_eq!(found.server_version.as_deref(), Some("v2.1.1867"));
+        assert!(!found.auth_required);
+
+        let locked = probe_url(&serve(1, |_| respond(401, "")));
+        assert!(!locked.reachable);
+        assert_eq!(locked.status, Some(401));
+        assert!(locked.auth_required);
+
+        assert_eq!(probe_url("localhost:4567"), ProbeResult::default());
+        assert_eq!(probe_url("not a url"), ProbeResult::default());
+    }
+}
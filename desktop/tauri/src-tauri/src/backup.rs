@@ -0,0 +1,344 @@
+use crate::http::{self, GraphqlError};
+use crate::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The whole file has to go through within it, on whatever link a remote server sits behind.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const PROGRESS_STEP_BYTES: u64 = 1024 * 1024;
+const BOUNDARY: &str = "suwayomi-launcher-backup-upload";
+/// Both name the same format, a gzip-compressed protobuf.
+const BACKUP_EXTENSIONS: &[&str] = &[".tachibk", ".proto.gz"];
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const RESTORE_MUTATION: &str = "mutation ($backup: Upload!) { restoreBackup(input: {backup: \
+     $backup}) { id status { state mangaProgress totalManga } } }";
+const STATUS_QUERY: &str =
+    "query ($id: String!) { restoreStatus(id: $id) { state mangaProgress totalManga } }";
+const UNSUPPORTED: &str = "this server has no API for restoring backups";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportPhase {
+    Uploading,
+    Restoring,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub phase: ImportPhase,
+    /// Bytes uploaded while uploading, manga restored while restoring.
+    pub done: u64,
+    pub total: u64,
+}
+
+/// Uploads the backup at `path` to the connected server and waits for the restore to
+/// finish, reporting both along the way. The file is streamed, never read into memory.
+pub fn import(path: &Path, progress: impl FnMut(ImportProgress)) -> Result<(), String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    import_at(base_url.trim_end_matches('/'), path, progress)
+}
+
+fn import_at(
+    base_url: &str,
+    path: &Path,
+    mut progress: impl FnMut(ImportProgress),
+) -> Result<(), String> {
+    let size = check_backup(path)?;
+    let file = File::open(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().replace(['"', '\r', '\n'], "_"))
+        .unwrap_or_default();
+
+    let (head, tail) = multipart_envelope(&file_name);
+    let body = head
+        .as_bytes()
+        .chain(ProgressReader {
+            inner: file,
+            sent: 0,
+            reported: 0,
+            total: size,
+            report: &mut progress,
+        })
+        .chain(tail.as_bytes());
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let response = http::post_stream(
+        &url,
+        &format!("multipart/form-data; boundary={BOUNDARY}"),
+        head.len() as u64 + size + tail.len() as u64,
+        body,
+        UPLOAD_TIMEOUT,
+    )
+    .ok_or_else(|| format!("server at {base_url} did not answer"))?;
+
+    // Servers from before the GraphQL API.
+    if response.status == 404 {
+        return Err(UNSUPPORTED.to_string());
+    }
+    let value: Value = serde_json::from_slice(&response.body)
+        .map_err(|_| format!("unexpected response from server (HTTP {})", response.status))?;
+    let Some(started) = value.pointer("/data/restoreBackup") else {
+        let message = value
+            .pointer("/errors/0/message")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if message.contains("restoreBackup") {
+            return Err(UNSUPPORTED.to_string());
+        }
+        return Err(format!("restoring the backup failed: {message}"));
+    };
+
+    let id = started.get("id").and_then(Value::as_str);
+    let mut status = started.get("status").cloned().unwrap_or_default();
+    let deadline = Instant::now() + RESTORE_TIMEOUT;
+    loop {
+        match status.get("state").and_then(Value::as_str) {
+            Some("SUCCESS") => break,
+            Some("FAILURE") => return Err("the server could not restore the backup".to_string()),
+            Some(_) => {}
+            None => return Err("the server did not say how the restore went".to_string()),
+        }
+        let count = |key| status.get(key).and_then(Value::as_u64).unwrap_or(0);
+        progress(ImportProgress {
+            phase: ImportPhase::Restoring,
+            done: count("mangaProgress"),
+            total: count("totalManga"),
+        });
+
+        // Older servers hand out no id to follow the restore with.
+        let Some(id) = id else {
+            break;
+        };
+        if Instant::now() >= deadline {
+            return Err("the server is still restoring the backup; check on it later".to_string());
+        }
+        thread::sleep(STATUS_POLL_INTERVAL);
+        status = restore_status(&url, id)?;
+    }
+
+    eprintln!("restored backup {} on {base_url}", path.display());
+    Ok(())
+}
+
+fn restore_status(url: &str, id: &str) -> Result<Value, String> {
+    let variables = serde_json::json!({ "id": id });
+    match http::graphql(url, STATUS_QUERY, Some(variables), STATUS_TIMEOUT) {
+        Ok(data) if !data["restoreStatus"].is_null() => Ok(data["restoreStatus"].clone()),
+        Ok(_) => Err("the server no longer knows about the restore".to_string()),
+        Err(GraphqlError::Unreachable) => {
+            Err("the server stopped answering during the restore".to_string())
+        }
+        Err(err) => Err(format!("following the restore failed: {err}")),
+    }
+}
+
+/// The file's size, once it is known to look like a backup: the right extension and
+/// gzip-compressed.
+fn check_backup(path: &Path) -> Result<u64, String> {
+    let metadata =
+        fs::metadata(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !BACKUP_EXTENSIONS
+        .iter()
+        .any(|extension| name.ends_with(extension))
+    {
+        return Err(format!(
+            "{} is not a backup; expected a .tachibk or .proto.gz file",
+            path.display()
+        ));
+    }
+
+    let mut magic = [0; 2];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    if magic != GZIP_MAGIC {
+        return Err(format!("{} is not a valid backup file", path.display()));
+    }
+    Ok(metadata.len())
+}
+
+/// What goes before and after the file in a GraphQL multipart request, which passes the
+/// upload as the `backup` variable.
+fn multipart_envelope(file_name: &str) -> (String, String) {
+    let operations = serde_json::json!({
+        "query": RESTORE_MUTATION,
+        "variables": { "backup": null },
+    });
+    let head = format!(
+        "--{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"operations\"\r\n\r\n\
+         {operations}\r\n\
+         --{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"map\"\r\n\r\n\
+         {{\"0\":[\"variables.backup\"]}}\r\n\
+         --{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"0\"; filename=\"{file_name}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    );
+    (head, format!("\r\n--{BOUNDARY}--\r\n"))
+}
+
+/// Reports upload progress as the file is read, about once per `PROGRESS_STEP_BYTES`.
+struct ProgressReader<'a, R, F> {
+    inner: R,
+    sent: u64,
+    reported: u64,
+    total: u64,
+    report: &'a mut F,
+}
+
+impl<R: Read, F: FnMut(ImportProgress)> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.sent += read as u64;
+        if self.sent - self.reported >= PROGRESS_STEP_BYTES
+            || (read == 0 && self.sent > self.reported)
+        {
+            self.reported = self.sent;
+            (self.report)(ImportProgress {
+                phase: ImportPhase::Uploading,
+                done: self.sent,
+                total: self.total,
+            });
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    fn write_backup(dir: &Path, name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).expect("write backup");
+        path
+    }
+
+    #[test]
+    fn only_gzip_files_with_a_backup_extension_are_accepted() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let backup = write_backup(dir.path(), "library.tachibk", &[0x1f, 0x8b, 8, 0]);
+        assert_eq!(check_backup(&backup), Ok(4));
+        let legacy = write_backup(dir.path(), "library.PROTO.GZ", &[0x1f, 0x8b]);
+        assert_eq!(check_backup(&legacy), Ok(2));
+
+        let text = write_backup(dir.path(), "notes.txt", &[0x1f, 0x8b]);
+        assert!(check_backup(&text).is_err());
+        let renamed = write_backup(dir.path(), "photo.tachibk", b"\x89PNG");
+        assert!(check_backup(&renamed)
+            .expect_err("not gzip")
+            .contains("not a valid backup"));
+        assert!(check_backup(&dir.path().join("missing.tachibk")).is_err());
+        assert!(check_backup(dir.path()).is_err());
+    }
+
+    #[test]
+    fn backup_is_uploaded_and_the_restore_followed_to_the_end() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let backup = write_backup(dir.path(), "library.tachibk", &[0x1f, 0x8b, 8, 0]);
+        let base_url = serve(2, |request| {
+            if request.contains("restoreBackup") {
+                assert!(
+                    request.contains("multipart/form-data; boundary="),
+                    "{request}"
+                );
+                assert!(
+                    request.contains("filename=\"library.tachibk\""),
+                    "{request}"
+                );
+                respond(
+                    200,
+                    r#"{"data":{"restoreBackup":{"id":"1","status":{"state":"RESTORING_MANGA","mangaProgress":1,"totalManga":3}}}}"#,
+                )
+            } else {
+                respond(
+                    200,
+                    r#"{"data":{"restoreStatus":{"state":"SUCCESS","mangaProgress":3,"totalManga":3}}}"#,
+                )
+            }
+        });
+
+        let mut reported = Vec::new();
+        import_at(&base_url, &backup, |progress| reported.push(progress)).expect("import");
+        assert_eq!(
+            reported,
+            [
+                ImportProgress {
+                    phase: ImportPhase::Uploading,
+                    done: 4,
+                    total: 4,
+                },
+                ImportProgress {
+                    phase: ImportPhase::Restoring,
+                    done: 1,
+                    total: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_restores_and_old_servers_are_errors() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let backup = write_backup(dir.path(), "library.tachibk", &[0x1f, 0x8b]);
+
+        let failing = serve(1, |_| {
+            respond(
+                200,
+                r#"{"data":{"restoreBackup":{"id":"1","status":{"state":"FAILURE"}}}}"#,
+            )
+        });
+        assert!(import_at(&failing, &backup, drop).is_err());
+
+        let forgotten = serve(2, |request| {
+            if request.contains("restoreBackup") {
+                respond(
+                    200,
+                    r#"{"data":{"restoreBackup":{"id":"1","status":{"state":"RESTORING_MANGA"}}}}"#,
+                )
+            } else {
+                respond(200, r#"{"data":{"restoreStatus":null}}"#)
+            }
+        });
+        assert!(import_at(&forgotten, &backup, drop).is_err());
+
+        let erroring = serve(2, |request| {
+            if request.contains("restoreBackup") {
+                respond(
+                    200,
+                    r#"{"data":{"restoreBackup":{"id":"1","status":{"state":"RESTORING_MANGA"}}}}"#,
+                )
+            } else {
+                respond(200, r#"{"errors":[{"message":"restore 1 not found"}]}"#)
+            }
+        });
+        assert!(import_at(&erroring, &backup, drop)
+            .expect_err("errors payload")
+            .contains("restore 1 not found"));
+
+        let legacy = serve(1, |_| respond(404, ""));
+        assert_eq!(
+            import_at(&legacy, &backup, drop),
+            Err(UNSUPPORTED.to_string())
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Scriptable stand-in for the server, see `fake_server.rs`. Only built with the
+//! `test-fake-server` feature.
+
+#[path = "../fake_server.rs"]
+mod fake_server;
+
+fn main() {
+    fake_server::run_from_env()
+}
@@ -0,0 +1,152 @@
+use std::path::Path;
+use std::time::Duration;
+use tauri::WebviewWindow;
+
+/// A full-page PDF of a long library can take a while to lay out.
+#[cfg_attr(not(windows), allow(dead_code))]
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Png,
+    Pdf,
+}
+
+impl CaptureFormat {
+    /// Picks the format from the file extension, so a save dialog's choice carries over.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("png") {
+            Some(Self::Png)
+        } else if extension.eq_ignore_ascii_case("pdf") {
+            Some(Self::Pdf)
+        } else {
+            None
+        }
+    }
+}
+
+/// Writes what `window` currently shows to `path`, as a PNG screenshot or a printed PDF.
+/// Blocks until the webview is done, so it must not run on the event loop.
+pub fn capture(window: &WebviewWindow, path: &Path, format: CaptureFormat) -> Result<(), String> {
+    platform::capture(window, path, format)
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{CaptureFormat, CAPTURE_TIMEOUT};
+    use base64::Engine;
+    use std::fs;
+    use std::path::{self, Path};
+    use std::sync::mpsc;
+    use tauri::WebviewWindow;
+    use webview2_com::Microsoft::Web::WebView2::Win32::{
+        ICoreWebView2, ICoreWebView2PrintSettings, ICoreWebView2_7,
+    };
+    use webview2_com::{CallDevToolsProtocolMethodCompletedHandler, PrintToPdfCompletedHandler};
+    use windows::core::{Interface, HSTRING};
+
+    pub fn capture(
+        window: &WebviewWindow,
+        path: &Path,
+        format: CaptureFormat,
+    ) -> Result<(), String> {
+        // WebView2 resolves relative paths against its own working directory.
+        let path = path::absolute(path).map_err(|err| err.to_string())?;
+        let (sender, receiver) = mpsc::channel::<Result<Option<String>, String>>();
+
+        let target = path.clone();
+        window
+            .with_webview(move |webview| {
+                let fail = sender.clone();
+                let started =
+                    unsafe { webview.controller().CoreWebView2() }.and_then(|core| match format {
+                        CaptureFormat::Png => screenshot(&core, sender),
+                        CaptureFormat::Pdf => print_to_pdf(&core, &target, sender),
+                    });
+                if let Err(err) = started {
+                    let _ = fail.send(Err(err.message()));
+                }
+            })
+            .map_err(|err| err.to_string())?;
+
+        let result = receiver
+            .recv_timeout(CAPTURE_TIMEOUT)
+            .map_err(|_| "the webview did not finish capturing the page".to_string())??;
+        match result {
+            Some(png) => {
+                let png = base64::engine::general_purpose::STANDARD
+                    .decode(png)
+                    .map_err(|err| format!("the webview returned a broken screenshot: {err}"))?;
+                fs::write(&path, png)
+                    .map_err(|err| format!("cannot write {}: {err}", path.display()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sends back the base64 PNG the DevTools protocol returns.
+    fn screenshot(
+        core: &ICoreWebView2,
+        sender: mpsc::Sender<Result<Option<String>, String>>,
+    ) -> windows::core::Result<()> {
+        let handler =
+            CallDevToolsProtocolMethodCompletedHandler::create(Box::new(move |result, json| {
+                let data = result.map_err(|err| err.message()).and_then(|()| {
+                    serde_json::from_str::<serde_json::Value>(&json)
+                        .ok()
+                        .and_then(|value| value.get("data")?.as_str().map(str::to_string))
+                        .ok_or_else(|| "the webview returned no screenshot".to_string())
+                });
+                let _ = sender.send(data.map(Some));
+                Ok(())
+            }));
+
+        unsafe {
+            core.CallDevToolsProtocolMethod(
+                &HSTRING::from("Page.captureScreenshot"),
+                &HSTRING::from(r#"{"format":"png"}"#),
+                &handler,
+            )
+        }
+    }
+
+    /// WebView2 writes the PDF itself and only reports whether it managed to.
+    fn print_to_pdf(
+        core: &ICoreWebView2,
+        path: &Path,
+        sender: mpsc::Sender<Result<Option<String>, String>>,
+    ) -> windows::core::Result<()> {
+        let core = core.cast::<ICoreWebView2_7>()?;
+        let shown = path.display().to_string();
+        let handler = PrintToPdfCompletedHandler::create(Box::new(move |result, written| {
+            let done = match result {
+                Ok(()) if written => Ok(None),
+                Ok(()) => Err(format!("the webview could not write {shown}")),
+                Err(err) => Err(err.message()),
+            };
+            let _ = sender.send(done);
+            Ok(())
+        }));
+
+        unsafe {
+            core.PrintToPdf(
+                &HSTRING::from(path.as_os_str()),
+                None::<&ICoreWebView2PrintSettings>,
+                &handler,
+            )
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use super::CaptureFormat;
+    use std::path::Path;
+    use tauri::WebviewWindow;
+
+    /// WKWebView and WebKitGTK only take snapshots through APIs Tauri does not expose.
+    pub fn capture(_: &WebviewWindow, _: &Path, _: CaptureFormat) -> Result<(), String> {
+        Err("capturing the page is not supported on this platform".to_string())
+    }
+}
@@ -0,0 +1,308 @@
+use once_cell::sync::Lazy;
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+static OPTIONS: Lazy<CliOptions> = Lazy::new(|| parse(env::args_os().skip(1)));
+
+/// Flags that act once and must not be repeated when the app relaunches itself: a second
+/// `--reset-database --confirm` would throw away the database the first run created.
+const ONE_SHOT_FLAGS: &[&str] = &[
+    "--reset-database",
+    "--confirm",
+    "--print-config",
+    "--dry-run",
+];
+/// Flags followed by a value, which is never a flag of its own.
+const VALUE_FLAGS: &[&str] = &[
+    "--runtime-root",
+    "--downloads-dir",
+    "--remote",
+    "--health-path",
+    "--timezone",
+    "--db-url",
+    "--db-user",
+    "--db-password-file",
+    "--bind",
+];
+
+/// Command-line options understood by the launcher. The first positional argument is the
+/// server URL; everything else is a `--flag` (either `--flag value` or `--flag=value`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliOptions {
+    pub base_url: Option<String>,
+    /// Use exactly this directory for `jre/` and `bin/Suwayomi-Server.jar` instead of
+    /// scanning the bundled candidate roots.
+    pub runtime_root: Option<PathBuf>,
+    /// Where the server stores downloaded chapters, instead of `server.downloadsPath`.
+    pub downloads_dir: Option<PathBuf>,
+    /// Print the `server.conf` the launcher resolves and how it was parsed, then exit.
+    pub print_config: bool,
+    /// Print the server command line and the environment it would inherit, then exit
+    /// without starting anything.
+    pub dry_run: bool,
+    /// Connect to this server only; never spawn the bundled one.
+    pub remote: Option<String>,
+    /// Health endpoint for the `--remote` server, e.g. when a gateway exposes it elsewhere.
+    pub health_path: Option<String>,
+    /// Address the spawned server listens on, e.g. `0.0.0.0` to expose it on the LAN.
+    pub bind: Option<String>,
+    /// Show the small server control panel instead of loading the web UI.
+    pub control_panel: bool,
+    /// Move the server database aside before starting, so the server creates a new one.
+    pub reset_database: bool,
+    /// Answers confirmation prompts (such as `--reset-database`) without a terminal.
+    pub confirm: bool,
+    /// Have the JVM write GC logs and a heap dump on `OutOfMemoryError` to the launcher's
+    /// log dir.
+    pub jvm_debug: bool,
+    /// Timezone ID for the server's JVM, e.g. `Europe/Berlin`, instead of the system's.
+    pub timezone: Option<String>,
+    /// External PostgreSQL database for the server, e.g. `postgresql://db:5432/suwayomi`.
+    pub db_url: Option<String>,
+    pub db_user: Option<String>,
+    /// File holding the database password; there is no flag for the password itself, since
+    /// command lines are visible to other users.
+    pub db_password_file: Option<PathBuf>,
+}
+
+pub fn options() -> &'static CliOptions {
+    &OPTIONS
+}
+
+/// Whether the opt-in environment variable `name` is set to `1` or `true`.
+pub fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> CliOptions {
+    let mut options = CliOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Arguments that are not valid Unicode can only be values, never flags.
+        let Some(text) = arg.to_str() else {
+            continue;
+        };
+
+        let (flag, inline_value) = match text.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(OsString::from(value))),
+            _ => (text, None),
+        };
+
+        match flag {
+            "--runtime-root" => {
+                if let Some(value) = inline_value.or_else(|| args.next()) {
+                    options.runtime_root = Some(PathBuf::from(value));
+                }
+            }
+            "--downloads-dir" => {
+                if let Some(value) = inline_value.or_else(|| args.next()) {
+                    options.downloads_dir = Some(PathBuf::from(value));
+                }
+            }
+            "--print-config" => options.print_config = true,
+            "--dry-run" => options.dry_run = true,
+            "--control-panel" => options.control_panel = true,
+            "--reset-database" => options.reset_database = true,
+            "--confirm" => options.confirm = true,
+            "--jvm-debug" => options.jvm_debug = true,
+            "--remote" => options.remote = flag_value(inline_value, &mut args),
+            "--health-path" => options.health_path = flag_value(inline_value, &mut args),
+            "--timezone" => options.timezone = flag_value(inline_value, &mut args),
+            "--db-url" => options.db_url = flag_value(inline_value, &mut args),
+            "--db-user" => options.db_user = flag_value(inline_value, &mut args),
+            "--db-password-file" => {
+                if let Some(value) = inline_value.or_else(|| args.next()) {
+                    options.db_password_file = Some(PathBuf::from(value));
+                }
+            }
+            "--bind" => {
+                options.bind = flag_value(inline_value, &mut args)
+                    .map(|addr| addr.trim().to_string())
+                    .filter(|addr| !addr.is_empty())
+            }
+            _ if flag.starts_with('-') => eprintln!("ignoring unknown launcher argument {flag}"),
+            _ => {
+                if options.base_url.is_none() {
+                    options.base_url = Some(text.to_string());
+                }
+            }
+        }
+    }
+
+    options
+}
+
+/// `args` without the [`ONE_SHOT_FLAGS`], for starting the app again with the same setup.
+pub fn relaunch_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut kept = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_str().unwrap_or_default();
+        if ONE_SHOT_FLAGS.contains(&text) {
+            continue;
+        }
+        let takes_value = VALUE_FLAGS.contains(&text);
+        kept.push(arg);
+        if takes_value {
+            kept.extend(args.next());
+        }
+    }
+
+    kept
+}
+
+fn flag_value(
+    inline_value: Option<OsString>,
+    args: &mut impl Iterator<Item = OsString>,
+) -> Option<String> {
+    let value = inline_value.or_else(|| args.next())?;
+    value.into_string().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_strs(args: &[&str]) -> CliOptions {
+        parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn parse_reads_positional_url_and_runtime_root() {
+        let options = parse_strs(&["http://127.0.0.1:4567", "--runtime-root", "/dev/suwayomi"]);
+
+        assert_eq!(options.base_url.as_deref(), Some("http://127.0.0.1:4567"));
+        assert_eq!(options.runtime_root, Some(PathBuf::from("/dev/suwayomi")));
+    }
+
+    #[test]
+    fn parse_accepts_inline_flag_values() {
+        let options = parse_strs(&["--runtime-root=/dev/suwayomi"]);
+
+        assert_eq!(options.runtime_root, Some(PathBuf::from("/dev/suwayomi")));
+        assert!(options.base_url.is_none());
+        assert_eq!(
+            parse_strs(&["--downloads-dir=/mnt/big/manga"]).downloads_dir,
+            Some(PathBuf::from("/mnt/big/manga"))
+        );
+    }
+
+    #[test]
+    fn parse_reads_remote_and_health_path() {
+        let options = parse_strs(&[
+            "--remote",
+            "https://manga.example.com",
+            "--health-path=/healthz",
+        ]);
+
+        assert_eq!(options.remote.as_deref(), Some("https://manga.example.com"));
+        assert_eq!(options.health_path.as_deref(), Some("/healthz"));
+        assert!(options.base_url.is_none());
+    }
+
+    #[test]
+    fn parse_reads_bind_address() {
+        assert_eq!(
+            parse_strs(&["--bind", "0.0.0.0"]).bind.as_deref(),
+            Some("0.0.0.0")
+        );
+        assert_eq!(parse_strs(&["--bind="]).bind, None);
+    }
+
+    #[test]
+    fn parse_reads_control_panel_flag() {
+        assert!(parse_strs(&["--control-panel"]).control_panel);
+        assert!(!parse_strs(&["http://127.0.0.1:4567"]).control_panel);
+    }
+
+    #[test]
+    fn parse_reads_reset_database_and_confirm() {
+        let options = parse_strs(&["--reset-database"]);
+        assert!(options.reset_database && !options.confirm);
+
+        let options = parse_strs(&["--reset-database", "--confirm"]);
+        assert!(options.reset_database && options.confirm);
+    }
+
+    #[test]
+    fn parse_reads_timezone() {
+        assert_eq!(
+            parse_strs(&["--timezone", "Asia/Tokyo"])
+                .timezone
+                .as_deref(),
+            Some("Asia/Tokyo")
+        );
+        assert_eq!(
+            parse_strs(&["--timezone=UTC"]).timezone.as_deref(),
+            Some("UTC")
+        );
+    }
+
+    #[test]
+    fn parse_reads_database_flags() {
+        let options = parse_strs(&[
+            "--db-url=postgresql://db:5432/suwayomi",
+            "--db-user",
+            "suwayomi",
+            "--db-password-file",
+            "/run/secrets/db",
+        ]);
+
+        assert_eq!(
+            options.db_url.as_deref(),
+            Some("postgresql://db:5432/suwayomi")
+        );
+        assert_eq!(options.db_user.as_deref(), Some("suwayomi"));
+        assert_eq!(
+            options.db_password_file,
+            Some(PathBuf::from("/run/secrets/db"))
+        );
+    }
+
+    #[test]
+    fn parse_reads_jvm_debug_flag() {
+        assert!(parse_strs(&["--jvm-debug"]).jvm_debug);
+        assert!(!parse_strs(&[]).jvm_debug);
+    }
+
+    #[test]
+    fn flag_values_are_not_mistaken_for_the_url() {
+        let options = parse_strs(&["--runtime-root", "/dev/suwayomi", "--unknown"]);
+
+        assert!(options.base_url.is_none());
+        assert!(!options.print_config);
+        assert!(!options.dry_run);
+    }
+
+    #[test]
+    fn relaunching_drops_one_shot_flags_only() {
+        let args = [
+            "http://127.0.0.1:4567",
+            "--reset-database",
+            "--confirm",
+            "--timezone",
+            "UTC",
+            "--dry-run",
+            "--db-user",
+            "--confirm",
+            "--control-panel",
+        ];
+        let kept = relaunch_args(args.iter().map(OsString::from));
+
+        assert_eq!(
+            kept,
+            [
+                "http://127.0.0.1:4567",
+                "--timezone",
+                "UTC",
+                "--db-user",
+                "--confirm",
+                "--control-panel",
+            ]
+            .map(OsString::from)
+        );
+    }
+}
@@ -0,0 +1,652 @@
+use crate::about::{self, AboutInfo, ProbeResult};
+use crate::backup;
+use crate::capture::{self, CaptureFormat};
+use crate::downloads::{self, ConcurrencyChange};
+use crate::events::{self, LauncherEvent};
+use crate::launcher::{
+    self, CommandError, CommandErrorKind, ConfigDiff, ConfigReport, LauncherError, ServerActivity,
+    ServerStatus, ServerTarget, StoredFailure,
+};
+use crate::library;
+use crate::log_level::{self, LogLevelChange};
+use crate::logs::{self, LogLine};
+use crate::pause::{self, PauseState};
+use crate::sources::{self, RepoStatus};
+use crate::supervisor::{self, ReconnectPolicy, RuntimeSettings};
+use crate::system_check::{self, CheckResult};
+use crate::usage::{self, DiskUsage};
+use crate::{cli, navigation, tray, updates};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+/// How long quitting may be held up asking the server what it is doing.
+const EXIT_ACTIVITY_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a window has to take up `quit-confirmation-requested` before the native
+/// dialog asks instead.
+const QUIT_PROMPT_SHOWN_TIMEOUT: Duration = Duration::from_secs(1);
+/// A prompt left unanswered this long, e.g. because its page was reloaded, counts as
+/// "keep running".
+const QUIT_PROMPT_ANSWER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+static EXIT_CONFIRMED: AtomicBool = AtomicBool::new(false);
+static EXIT_PROMPT_OPEN: AtomicBool = AtomicBool::new(false);
+/// Where [`quit_prompt_shown`] and [`answer_quit_prompt`] report to while a frontend
+/// prompt is pending.
+static QUIT_PROMPT: Mutex<Option<Sender<QuitPromptReply>>> = Mutex::new(None);
+
+enum QuitPromptReply {
+    Shown,
+    Answered { quit: bool },
+}
+
+#[tauri::command]
+pub async fn start_server(app: AppHandle) -> Result<String, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let bootstrap = run_blocking(move || launcher::bootstrap(resource_dir)).await?;
+
+    navigate_main_window(&app, &bootstrap.base_url)?;
+    Ok(bootstrap.base_url)
+}
+
+#[tauri::command]
+pub async fn restart_server(app: AppHandle) -> Result<String, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let bootstrap = run_blocking(move || launcher::restart_server(resource_dir)).await?;
+
+    navigate_main_window(&app, &bootstrap.base_url)?;
+    Ok(bootstrap.base_url)
+}
+
+/// Stops the server the app started (or adopted). Returns `false` when none was running.
+#[tauri::command]
+pub async fn stop_server() -> Result<bool, CommandError> {
+    tauri::async_runtime::spawn_blocking(launcher::stop_server)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Re-runs discovery, spawning and the health wait from scratch, for the error page's
+/// retry button and the first-run wizard.
+#[tauri::command]
+pub async fn rediscover(app: AppHandle) -> Result<String, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let bootstrap = run_blocking(move || launcher::rediscover(resource_dir)).await?;
+
+    navigate_main_window(&app, &bootstrap.base_url)?;
+    Ok(bootstrap.base_url)
+}
+
+/// Applies external edits to `server.conf`, restarting the server only if a changed key
+/// needs it. Returns what changed so the UI can show it.
+#[tauri::command]
+pub async fn reload_config(app: AppHandle) -> Result<ConfigDiff, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let diff = run_blocking(move || launcher::reload_config(resource_dir)).await?;
+
+    if let Some(base_url) = &diff.base_url {
+        navigate_main_window(&app, base_url)?;
+    }
+    Ok(diff)
+}
+
+#[tauri::command]
+pub async fn connect_to(app: AppHandle, url: String) -> Result<String, CommandError> {
+    let bootstrap = run_blocking(move || launcher::connect_to(&url)).await?;
+
+    navigate_main_window(&app, &bootstrap.base_url)?;
+    Ok(bootstrap.base_url)
+}
+
+/// Moves the app between its local server and remote ones without restarting it. A local
+/// target is started if needed; `stop_previous` stops the local server when leaving it for
+/// a remote one. Emits `server-switched` with the new base URL.
+#[tauri::command]
+pub async fn switch_server(
+    app: AppHandle,
+    target: ServerTarget,
+    stop_previous: Option<bool>,
+) -> Result<String, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let stop_previous = stop_previous.unwrap_or(false);
+    let bootstrap =
+        run_blocking(move || launcher::switch_server(&target, stop_previous, resource_dir)).await?;
+
+    navigate_main_window(&app, &bootstrap.base_url)?;
+    // The connection monitor and tray should reflect the new server right away.
+    supervisor::wake();
+    let _ = app.emit("server-switched", &bootstrap.base_url);
+    Ok(bootstrap.base_url)
+}
+
+/// Checks a URL typed into the connect form without switching to it, so the form can give
+/// feedback before `connect_to` is called.
+#[tauri::command]
+pub async fn probe_url(url: String) -> Result<ProbeResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || about::probe_url(&url))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Wipes the webview's cookies, local storage, IndexedDB and cache and reloads the page.
+/// Server data is untouched, but the user is logged out of the web UI, so this asks for
+/// confirmation first. Returns `false` when the user cancels.
+#[tauri::command]
+pub async fn reset_app_state(app: AppHandle) -> Result<bool, CommandError> {
+    let confirmed = app
+        .dialog()
+        .message(
+            "This clears everything the app has stored in its web view (cookies, saved UI \
+             settings and cache) and logs you out. Your library and server data are not \
+             affected.",
+        )
+        .title("Reset app state")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Reset".to_string(),
+            "Cancel".to_string(),
+        ))
+        .blocking_show();
+
+    if !confirmed {
+        return Ok(false);
+    }
+
+    let window = main_window(&app)?;
+    window
+        .clear_all_browsing_data()
+        .map_err(|err| CommandError::internal(err.to_string()))?;
+    window
+        .reload()
+        .map_err(|err| CommandError::internal(err.to_string()))?;
+
+    Ok(true)
+}
+
+/// Why the last attempt to start or find a server failed, with the offending path, port or
+/// URL broken out; `None` once one has succeeded.
+#[tauri::command]
+pub fn bootstrap_error() -> Option<LauncherError> {
+    launcher::bootstrap_error()
+}
+
+/// How startup failed the last time the app ran, if it did, for reporting a failure the
+/// user only gets around to after reopening the app.
+#[tauri::command]
+pub fn previous_failure() -> Option<StoredFailure> {
+    launcher::previous_failure()
+}
+
+/// How much space the server's data directory takes up, with downloads, the database and
+/// thumbnails broken out. Large libraries may only be partly measured; see `complete`.
+#[tauri::command]
+pub async fn data_dir_usage() -> Result<DiskUsage, CommandError> {
+    tauri::async_runtime::spawn_blocking(usage::data_dir_usage)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Checks Java, the server jar, the port, the data directory, free disk space and the
+/// webview runtime in one go, for the system check panel.
+#[tauri::command]
+pub async fn system_check(app: AppHandle) -> Result<Vec<CheckResult>, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let webview = tauri::webview_version().map_err(|err| err.to_string());
+    tauri::async_runtime::spawn_blocking(move || system_check::run(resource_dir, webview))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Which server the app is using and whether it is up.
+#[tauri::command]
+pub async fn server_status() -> Result<ServerStatus, CommandError> {
+    tauri::async_runtime::spawn_blocking(launcher::server_status)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Makes the server re-fetch its extension repos.
+#[tauri::command]
+pub async fn refresh_sources() -> Result<(), CommandError> {
+    tauri::async_runtime::spawn_blocking(sources::refresh)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::internal)
+}
+
+/// Checks whether the server's extension repos can be reached, for "sources won't load".
+#[tauri::command]
+pub async fn check_repos() -> Result<Vec<RepoStatus>, CommandError> {
+    tauri::async_runtime::spawn_blocking(sources::check_repos)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::internal)
+}
+
+/// Pauses (or resumes) the server's downloads and library updates while it keeps running.
+/// Fails on servers without the API for it. Emits `pause-changed` with the new state.
+#[tauri::command]
+pub async fn pause_server(app: AppHandle, paused: bool) -> Result<PauseState, CommandError> {
+    let state = tauri::async_runtime::spawn_blocking(move || pause::set(paused))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::internal)?;
+
+    let _ = app.emit("pause-changed", &state);
+    Ok(state)
+}
+
+/// How many sources the server downloads chapters from at once.
+#[tauri::command]
+pub async fn get_download_concurrency() -> Result<u32, CommandError> {
+    tauri::async_runtime::spawn_blocking(downloads::get)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::internal)
+}
+
+/// Changes the download concurrency (1 to 20), restarting the server if it cannot take the
+/// change live.
+#[tauri::command]
+pub async fn set_download_concurrency(
+    app: AppHandle,
+    concurrency: u32,
+) -> Result<ConcurrencyChange, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let change =
+        tauri::async_runtime::spawn_blocking(move || downloads::set(concurrency, resource_dir))
+            .await
+            .map_err(|err| CommandError::internal(err.to_string()))?
+            .map_err(CommandError::internal)?;
+
+    if let Some(base_url) = &change.base_url {
+        navigate_main_window(&app, base_url)?;
+    }
+    Ok(change)
+}
+
+/// Raises or lowers the server's logging (`error` … `trace`) for diagnosing a problem.
+#[tauri::command]
+pub async fn set_server_log_level(
+    app: AppHandle,
+    level: String,
+) -> Result<LogLevelChange, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+    let change = tauri::async_runtime::spawn_blocking(move || log_level::set(&level, resource_dir))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::internal)?;
+
+    if let Some(base_url) = &change.base_url {
+        navigate_main_window(&app, base_url)?;
+    }
+    Ok(change)
+}
+
+/// Shows a file the launcher produced, such as a database backup or a log, selected in the
+/// system file manager.
+#[tauri::command]
+pub async fn reveal_in_file_manager(path: String) -> Result<(), CommandError> {
+    tauri::async_runtime::spawn_blocking(move || navigation::reveal(Path::new(&path)))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(|err| CommandError {
+            kind: match err.kind() {
+                io::ErrorKind::NotFound => CommandErrorKind::MissingFile,
+                _ => CommandErrorKind::Internal,
+            },
+            message: err.to_string(),
+        })
+}
+
+/// Saves the main window's page to `path`: a PNG screenshot, or a PDF when `path` ends in
+/// `.pdf`. Only the Windows webview can be captured; elsewhere this fails as `Unsupported`.
+#[tauri::command]
+pub async fn capture_page(app: AppHandle, path: String) -> Result<(), CommandError> {
+    let path = PathBuf::from(path);
+    let format = CaptureFormat::for_path(&path).ok_or_else(|| CommandError {
+        kind: CommandErrorKind::Unsupported,
+        message: format!("{} is not a .png or .pdf file", path.display()),
+    })?;
+    let window = main_window(&app)?;
+
+    tauri::async_runtime::spawn_blocking(move || capture::capture(&window, &path, format))
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(|message| CommandError {
+            kind: if cfg!(windows) {
+                CommandErrorKind::Internal
+            } else {
+                CommandErrorKind::Unsupported
+            },
+            message,
+        })
+}
+
+/// Restores the backup at `path` on the connected server, emitting `backup-import-progress`
+/// while it uploads and restores, then reloads the web UI to show the restored library.
+#[tauri::command]
+pub async fn import_backup(app: AppHandle, path: String) -> Result<(), CommandError> {
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        backup::import(Path::new(&path), |progress| {
+            let _ = handle.emit("backup-import-progress", progress);
+        })
+    })
+    .await
+    .map_err(|err| CommandError::internal(err.to_string()))?
+    .map_err(CommandError::internal)?;
+
+    if !cli::options().control_panel {
+        main_window(&app)?
+            .reload()
+            .map_err(|err| CommandError::internal(err.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Checks every manga in the connected server's library for new chapters, emitting
+/// `library-update-progress` as manga are done, and returns once the server has finished.
+#[tauri::command]
+pub async fn update_library(app: AppHandle) -> Result<(), CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        library::update(|progress| {
+            let _ = app.emit("library-update-progress", progress);
+        })
+    })
+    .await
+    .map_err(|err| CommandError::internal(err.to_string()))?
+    .map_err(CommandError::internal)
+}
+
+/// Copies the server URL in use to the clipboard and returns it.
+#[tauri::command]
+pub fn copy_server_url(app: AppHandle) -> Result<String, CommandError> {
+    tray::copy_server_url(&app).map_err(CommandError::internal)
+}
+
+/// Recent launcher/server lifecycle events, oldest first.
+#[tauri::command]
+pub fn recent_events() -> Vec<LauncherEvent> {
+    events::recent()
+}
+
+/// Launcher events and managed server output in one list, oldest first.
+#[tauri::command]
+pub fn combined_logs() -> Vec<LogLine> {
+    logs::combined()
+}
+
+/// Downloads and verifies server release `version` and stages it next to the current jar.
+/// It replaces the running jar the next time the server starts, e.g. via `restart_server`.
+/// Only ever triggered by an explicit user action.
+#[tauri::command]
+pub async fn stage_server_update(app: AppHandle, version: String) -> Result<String, CommandError> {
+    let resource_dir = app.path().resource_dir().ok();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let jar = launcher::server_jar_path(resource_dir).map_err(CommandError::from)?;
+        updates::download_and_stage(&jar, &version)
+            .map(|staged| staged.display().to_string())
+            .map_err(|err| CommandError {
+                kind: CommandErrorKind::UpdateFailed,
+                message: err.to_string(),
+            })
+    })
+    .await
+    .map_err(|err| CommandError::internal(err.to_string()))?
+}
+
+/// Launcher version plus the build info reported by the connected server.
+#[tauri::command]
+pub async fn server_about() -> Result<AboutInfo, CommandError> {
+    tauri::async_runtime::spawn_blocking(about::about)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))
+}
+
+/// Shows [`server_about`] in a native About dialog.
+#[tauri::command]
+pub async fn show_about(app: AppHandle) -> Result<(), CommandError> {
+    let info = server_about().await?;
+
+    app.dialog()
+        .message(info.to_string())
+        .title("About Suwayomi")
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::Ok)
+        .blocking_show();
+
+    Ok(())
+}
+
+/// The `server.conf` the launcher resolves, its raw contents and the values parsed from it.
+#[tauri::command]
+pub fn effective_config() -> ConfigReport {
+    launcher::config_report()
+}
+
+/// The timeouts and intervals in effect and whether each is a default or came from the
+/// environment.
+#[tauri::command]
+pub fn runtime_settings() -> RuntimeSettings {
+    supervisor::runtime_settings()
+}
+
+/// The reconnect grace period, probe interval, failure threshold and restart limit in
+/// effect.
+#[tauri::command]
+pub fn get_reconnect_policy() -> ReconnectPolicy {
+    supervisor::reconnect_policy()
+}
+
+/// Saves a new reconnect policy and applies it right away. Returns the policy in effect,
+/// which keeps any value set through an environment variable.
+#[tauri::command]
+pub fn set_reconnect_policy(policy: ReconnectPolicy) -> Result<ReconnectPolicy, CommandError> {
+    supervisor::set_reconnect_policy(policy).map_err(CommandError::internal)
+}
+
+/// Quits without asking whether a busy server may be stopped.
+#[tauri::command]
+pub fn confirm_quit(app: AppHandle) {
+    EXIT_CONFIRMED.store(true, Ordering::SeqCst);
+    app.exit(0);
+}
+
+/// Tells the launcher that a window is showing its own prompt for a
+/// `quit-confirmation-requested` event, so the native dialog is not shown as well.
+#[tauri::command]
+pub fn quit_prompt_shown() {
+    reply_to_quit_prompt(QuitPromptReply::Shown);
+}
+
+/// The user's answer to a `quit-confirmation-requested` prompt: quit, or keep running.
+#[tauri::command]
+pub fn answer_quit_prompt(quit: bool) {
+    reply_to_quit_prompt(QuitPromptReply::Answered { quit });
+}
+
+fn reply_to_quit_prompt(reply: QuitPromptReply) {
+    if let Some(prompt) = QUIT_PROMPT
+        .lock()
+        .expect("quit prompt mutex poisoned")
+        .as_ref()
+    {
+        let _ = prompt.send(reply);
+    }
+}
+
+/// Stops the server (asking it to terminate, then killing it if it does not) and quits
+/// once it is gone. A `shutting-down` event is emitted first so the UI can show progress
+/// while the server takes its time.
+#[tauri::command]
+pub async fn quit_app(app: AppHandle) -> Result<(), CommandError> {
+    let _ = app.emit("shutting-down", ());
+
+    tauri::async_runtime::spawn_blocking(launcher::shutdown_child_process)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?;
+
+    // Nothing is left running that the busy-server prompt could protect.
+    EXIT_CONFIRMED.store(true, Ordering::SeqCst);
+    app.exit(0);
+    Ok(())
+}
+
+/// Stops the server like [`quit_app`], then starts the launcher again as a new process
+/// with the same arguments, less one-shot ones like `--reset-database`. Unlike
+/// `restart_server`, everything held in memory starts over, e.g. to pick up a staged app
+/// update.
+#[tauri::command]
+pub async fn relaunch_app(app: AppHandle) -> Result<(), CommandError> {
+    // Looked up before anything is stopped, so a launcher that cannot find itself keeps
+    // its server.
+    let exe = std::env::current_exe()
+        .map_err(|err| CommandError::internal(format!("cannot start the app again: {err}")))?;
+    let _ = app.emit("shutting-down", ());
+
+    // Stopped before the new process exists, so it cannot find the old server half alive
+    // or leave it orphaned.
+    tauri::async_runtime::spawn_blocking(launcher::shutdown_child_process)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?;
+
+    // Not `request_restart`, which would repeat one-shot flags such as `--reset-database`.
+    let spawned = std::process::Command::new(exe)
+        .args(cli::relaunch_args(std::env::args_os().skip(1)))
+        .spawn();
+    if let Err(err) = spawned {
+        // This process keeps running after all, and needs its server back.
+        let resource_dir = app.path().resource_dir().ok();
+        run_blocking(move || launcher::cancel_shutdown(resource_dir)).await?;
+        return Err(CommandError::internal(format!(
+            "cannot start the app again: {err}"
+        )));
+    }
+
+    EXIT_CONFIRMED.store(true, Ordering::SeqCst);
+    app.exit(0);
+    Ok(())
+}
+
+/// Decides whether a close/exit request has to be held back to check on the managed
+/// server. While one runs, the request is held and the server is asked, off the event
+/// loop, whether it is downloading or updating the library; if it is, the user is asked
+/// to confirm, see [`confirm_quit_with_user`]. The app then exits through
+/// [`confirm_quit`], unless the user chose to keep it running. Servers that cannot be
+/// queried do not hold anything up.
+pub fn hold_exit_for_confirmation(app: &AppHandle) -> bool {
+    if EXIT_CONFIRMED.load(Ordering::SeqCst)
+        || launcher::managed_server_state().is_none_or(|state| state.exited)
+    {
+        return false;
+    }
+
+    if EXIT_PROMPT_OPEN.swap(true, Ordering::SeqCst) {
+        return true;
+    }
+
+    // Both the query and the dialog block, which must not happen on the event loop.
+    let app = app.clone();
+    thread::spawn(move || {
+        let activity = launcher::managed_server_activity(EXIT_ACTIVITY_TIMEOUT)
+            .filter(ServerActivity::is_busy);
+        let confirmed = activity.is_none_or(|activity| confirm_quit_with_user(&app, activity));
+        EXIT_PROMPT_OPEN.store(false, Ordering::SeqCst);
+
+        if confirmed {
+            confirm_quit(app);
+        }
+    });
+
+    true
+}
+
+/// Emits `quit-confirmation-requested` with the server's `activity` for a window to ask
+/// with its own prompt, acknowledged through [`quit_prompt_shown`] and answered through
+/// [`answer_quit_prompt`]. When no window takes it up, a native dialog asks instead.
+fn confirm_quit_with_user(app: &AppHandle, activity: ServerActivity) -> bool {
+    let (sender, replies) = mpsc::channel();
+    *QUIT_PROMPT.lock().expect("quit prompt mutex poisoned") = Some(sender);
+    let _ = app.emit("quit-confirmation-requested", activity);
+
+    let answer = match replies.recv_timeout(QUIT_PROMPT_SHOWN_TIMEOUT) {
+        Ok(QuitPromptReply::Answered { quit }) => Some(quit),
+        Ok(QuitPromptReply::Shown) => loop {
+            match replies.recv_timeout(QUIT_PROMPT_ANSWER_TIMEOUT) {
+                Ok(QuitPromptReply::Answered { quit }) => break Some(quit),
+                Ok(QuitPromptReply::Shown) => continue,
+                Err(_) => break Some(false),
+            }
+        },
+        Err(_) => None,
+    };
+    *QUIT_PROMPT.lock().expect("quit prompt mutex poisoned") = None;
+
+    answer.unwrap_or_else(|| confirm_quit_dialog(app, activity))
+}
+
+fn confirm_quit_dialog(app: &AppHandle, activity: ServerActivity) -> bool {
+    let mut running = Vec::new();
+    if activity.active_downloads > 0 {
+        running.push(format!("{} chapter download(s)", activity.active_downloads));
+    }
+    if activity.library_update_running {
+        running.push("a library update".to_string());
+    }
+
+    app.dialog()
+        .message(format!(
+            "The server is still working on {}. Quitting now stops the server and may leave \
+             partially downloaded chapters behind.",
+            running.join(" and ")
+        ))
+        .title("Downloads in progress \u{2014} quit anyway?")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Quit".to_string(),
+            "Keep running".to_string(),
+        ))
+        .blocking_show()
+}
+
+async fn run_blocking<T, F>(task: F) -> Result<T, CommandError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, LauncherError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|err| CommandError::internal(err.to_string()))?
+        .map_err(CommandError::from)
+}
+
+fn main_window(app: &AppHandle) -> Result<WebviewWindow, CommandError> {
+    app.get_webview_window("main")
+        .ok_or_else(|| CommandError::internal("main window is not open"))
+}
+
+/// Points the main window at the (possibly new) server URL, except in control-panel mode
+/// where the window keeps showing the panel.
+pub fn navigate_main_window(app: &AppHandle, base_url: &str) -> Result<(), CommandError> {
+    let url = url::Url::parse(base_url).map_err(|err| CommandError::internal(err.to_string()))?;
+
+    let window = app
+        .get_webview_window("main")
+        .filter(|_| !cli::options().control_panel);
+    if let Some(window) = window {
+        window
+            .navigate(url)
+            .map_err(|err| CommandError::internal(err.to_string()))?;
+    }
+
+    tray::show_base_url(app, base_url);
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use crate::http::{self, GraphqlError};
+use crate::launcher;
+use crate::server_settings::{self, Applied, Setting, SETTINGS_TIMEOUT};
+use serde::Serialize;
+use serde_json::Value;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+const CONCURRENCY: Setting = Setting {
+    field: "maxSourcesInParallel",
+    conf_key: "server.maxSourcesInParallel",
+    description: "download concurrency",
+};
+/// The server's own default and the range its settings screen allows.
+const DEFAULT_CONCURRENCY: u32 = 6;
+const CONCURRENCY_RANGE: RangeInclusive<u32> = 1..=20;
+const CONCURRENCY_QUERY: &str = "query { settings { maxSourcesInParallel } }";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyChange {
+    pub concurrency: u32,
+    pub applied: Applied,
+    /// Set after a restart, since the server may come back at a different URL.
+    pub base_url: Option<String>,
+}
+
+/// How many sources the server downloads from at once, from the live settings when the
+/// server has them and `server.conf` otherwise.
+pub fn get() -> Result<u32, String> {
+    if let Some(base_url) = launcher::active_base_url() {
+        if let Some(concurrency) = get_live(base_url.trim_end_matches('/'))? {
+            return Ok(concurrency);
+        }
+    }
+
+    Ok(launcher::server_conf_value(CONCURRENCY.conf_key)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_CONCURRENCY))
+}
+
+/// Changes the download concurrency, live when the server has a settings API and through
+/// `server.conf` (restarting a managed server) otherwise.
+pub fn set(concurrency: u32, resource_dir: Option<PathBuf>) -> Result<ConcurrencyChange, String> {
+    if !CONCURRENCY_RANGE.contains(&concurrency) {
+        return Err(format!(
+            "download concurrency must be between {} and {}, got {concurrency}",
+            CONCURRENCY_RANGE.start(),
+            CONCURRENCY_RANGE.end()
+        ));
+    }
+
+    let change = server_settings::change(&CONCURRENCY, concurrency.into(), resource_dir)?;
+    Ok(ConcurrencyChange {
+        concurrency,
+        applied: change.applied,
+        base_url: change.base_url,
+    })
+}
+
+/// `Ok(None)` when the server has no settings API to ask.
+fn get_live(base_url: &str) -> Result<Option<u32>, String> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    match http::graphql(&url, CONCURRENCY_QUERY, None, SETTINGS_TIMEOUT) {
+        Ok(data) => Ok(data
+            .pointer("/settings/maxSourcesInParallel")
+            .and_then(Value::as_u64)
+            .and_then(|concurrency| u32::try_from(concurrency).ok())),
+        Err(GraphqlError::Unreachable) => Err(format!("server at {base_url} did not answer")),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    #[test]
+    fn concurrency_outside_the_server_range_is_rejected() {
+        for concurrency in [0, 21] {
+            let err = set(concurrency, None).expect_err("out of range");
+            assert!(err.contains("between 1 and 20"), "{err}");
+        }
+    }
+
+    #[test]
+    fn concurrency_is_read_and_changed_through_the_settings_api() {
+        let live = serve(2, |request| {
+            if request.contains("setSettings") {
+                assert!(request.contains("maxSourcesInParallel: 3"), "{request}");
+                respond(
+                    200,
+                    r#"{"data":{"setSettings":{"settings":{"maxSourcesInParallel":3}}}}"#,
+                )
+            } else {
+                respond(200, r#"{"data":{"settings":{"maxSourcesInParallel":6}}}"#)
+            }
+        });
+        assert_eq!(get_live(&live), Ok(Some(6)));
+        assert_eq!(
+            server_settings::set_live(&live, &CONCURRENCY, &3.into()),
+            Ok(true)
+        );
+
+        let legacy = serve(2, |_| respond(404, ""));
+        assert_eq!(get_live(&legacy), Ok(None));
+        assert_eq!(
+            server_settings::set_live(&legacy, &CONCURRENCY, &3.into()),
+            Ok(false)
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const EVENT_LOG_CAPACITY: usize = 200;
+
+static EVENT_LOG: Lazy<EventLog> = Lazy::new(|| EventLog::new(EVENT_LOG_CAPACITY));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    /// The server process was spawned.
+    Started,
+    /// The server passed its health check.
+    Ready,
+    /// Connected to a server we did not spawn.
+    Connected,
+    /// The server process exited unexpectedly.
+    Crashed,
+    /// The server stayed unhealthy for longer than the supervisor tolerates.
+    Unresponsive,
+    Restarted,
+    Stopped,
+    Failed,
+    /// The server is a major version the launcher was not built for.
+    UnsupportedVersion,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LauncherEvent {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+/// Fixed-size, thread-safe ring buffer of lifecycle events; the oldest events are dropped
+/// once `capacity` is reached.
+#[derive(Debug)]
+pub struct EventLog {
+    capacity: usize,
+    events: Mutex<VecDeque<LauncherEvent>>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, kind: EventKind, message: impl Into<String>) {
+        let event = LauncherEvent {
+            timestamp_ms: now_ms(),
+            kind,
+            message: message.into(),
+        };
+
+        let mut events = self.events.lock().expect("event log mutex poisoned");
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Events in chronological order, oldest first.
+    pub fn snapshot(&self) -> Vec<LauncherEvent> {
+        let events = self.events.lock().expect("event log mutex poisoned");
+        events.iter().cloned().collect()
+    }
+}
+
+pub fn record(kind: EventKind, message: impl Into<String>) {
+    EVENT_LOG.push(kind, message);
+}
+
+pub fn recent() -> Vec<LauncherEvent> {
+    EVENT_LOG.snapshot()
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(millis)
+        .unwrap_or_default()
+}
+
+/// `duration` in whole milliseconds, saturating at `u64::MAX` rather than wrapping.
+pub(crate) fn millis(duration: Duration) -> u64 {
+    duration.as_millis().min(u128::from(u64::MAX)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_log_drops_oldest_events_beyond_capacity() {
+        let log = EventLog::new(3);
+
+        for index in 0..5 {
+            log.push(EventKind::Started, format!("event {index}"));
+        }
+
+        let messages: Vec<_> = log
+            .snapshot()
+            .into_iter()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(messages, ["event 2", "event 3", "event 4"]);
+    }
+
+    #[test]
+    fn event_log_is_shared_across_threads() {
+        let log = std::sync::Arc::new(EventLog::new(100));
+
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        log.push(EventKind::Ready, "ready");
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().expect("writer thread");
+        }
+
+        let events = log.snapshot();
+        assert_eq!(events.len(), 40);
+        assert!(events.iter().all(|event| event.timestamp_ms > 0));
+    }
+}
@@ -0,0 +1,74 @@
+use std::env;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ABOUT_BODY: &str = r#"{"name":"Suwayomi-Server","version":"v0.0.0-fake","buildType":"Fake"}"#;
+
+/// Stand-in for the server, launched by the `test-fake-server` spawner instead of the JVM.
+/// It is scripted through the environment the spawner sets:
+///
+/// - `FAKE_SERVER_PORT`: loopback port to listen on
+/// - `FAKE_SERVER_READY_AFTER_MS`: answer 503 until this long after start
+/// - `FAKE_SERVER_HEALTH_STATUS`: status of every response once ready (default 200)
+/// - `FAKE_SERVER_EXIT_AFTER_MS`: exit with status 1 after this long, like a crashed JVM
+///
+/// Signals keep their default disposition, so SIGTERM and a kill stop it the way they
+/// stop the real server.
+pub fn run_from_env() -> ! {
+    let number = |name: &str| {
+        env::var(name)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    let Some(port) = number("FAKE_SERVER_PORT").and_then(|port| u16::try_from(port).ok()) else {
+        eprintln!("fake server: FAKE_SERVER_PORT is not set");
+        process::exit(2);
+    };
+    let ready_after = Duration::from_millis(number("FAKE_SERVER_READY_AFTER_MS").unwrap_or(0));
+    let health_status = number("FAKE_SERVER_HEALTH_STATUS").unwrap_or(200);
+
+    if let Some(exit_after) = number("FAKE_SERVER_EXIT_AFTER_MS") {
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(exit_after));
+            process::exit(1);
+        });
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("fake server: cannot listen on port {port}: {err}");
+            process::exit(2);
+        }
+    };
+
+    let started = Instant::now();
+    for stream in listener.incoming().flatten() {
+        let status = if started.elapsed() < ready_after {
+            503
+        } else {
+            health_status
+        };
+        respond(stream, status);
+    }
+
+    process::exit(0)
+}
+
+/// Every path gets the same answer: the launcher only ever asks for health and about.
+fn respond(mut stream: TcpStream, status: u64) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+    let mut request = [0; 4096];
+    let _ = stream.read(&mut request);
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status} Fake\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{ABOUT_BODY}",
+        ABOUT_BODY.len()
+    );
+}
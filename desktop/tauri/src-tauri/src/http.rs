@@ -0,0 +1,690 @@
+use crate::cli;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use once_cell::sync::Lazy;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use serde_json::Value;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use url::{Host, Url};
+
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// `Authorization` header for requests to the server: `SUWAYOMI_API_KEY` as a bearer token,
+/// otherwise `SUWAYOMI_USERNAME` and `SUWAYOMI_PASSWORD` as basic auth. The key and the
+/// password can also be read from the file named by `SUWAYOMI_API_KEY_FILE` or
+/// `SUWAYOMI_PASSWORD_FILE`, which wins over the inline variable.
+static AUTHORIZATION: Lazy<Option<String>> = Lazy::new(|| {
+    authorization().unwrap_or_else(|err| {
+        eprintln!("not sending server credentials: {err}");
+        None
+    })
+});
+
+/// Verifies certificates against the bundled web PKI roots.
+static VERIFYING_AGENT: Lazy<ureq::Agent> = Lazy::new(|| ureq::AgentBuilder::new().build());
+
+/// Accepts any certificate; only used for loopback hosts or when explicitly opted into.
+static RELAXED_AGENT: Lazy<ureq::Agent> = Lazy::new(|| {
+    ureq::AgentBuilder::new()
+        .tls_config(Arc::new(insecure_tls_config()))
+        .build()
+});
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Decoded (gzip/deflate-free) body, capped at `MAX_BODY_BYTES`.
+    pub body: Vec<u8>,
+}
+
+/// GETs `url` through the shared agent. Non-2xx statuses are returned as responses rather
+/// than errors; `None` means the server could not be reached at all.
+///
+/// Requests are plain HTTP/1.1 (no ALPN is offered, so TLS proxies fall back to it). Proxies
+/// that route on virtual host can be satisfied with `SUWAYOMI_HOST_HEADER`.
+///
+/// The server credentials (see [`AUTHORIZATION`]) are sent along, so third-party URLs go
+/// through [`get_anonymous`] instead.
+pub fn get(url: &str, timeout: Duration) -> Option<HttpResponse> {
+    send(
+        url,
+        timeout,
+        host_header().as_deref(),
+        AUTHORIZATION.as_deref(),
+        None,
+    )
+}
+
+/// [`get`] without the server credentials.
+pub fn get_anonymous(url: &str, timeout: Duration) -> Option<HttpResponse> {
+    send(url, timeout, host_header().as_deref(), None, None)
+}
+
+/// POSTs a JSON `body` to `url`, with the same semantics as [`get`].
+pub fn post_json(url: &str, body: &str, timeout: Duration) -> Option<HttpResponse> {
+    send(
+        url,
+        timeout,
+        host_header().as_deref(),
+        AUTHORIZATION.as_deref(),
+        Some(body),
+    )
+}
+
+/// Why a [`graphql`] request returned no data.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GraphqlError {
+    #[error("the server did not answer")]
+    Unreachable,
+    /// A server from before the GraphQL API.
+    #[error("the server has no GraphQL API")]
+    NotFound,
+    #[error("unexpected response from server (HTTP {status})")]
+    InvalidResponse { status: u16 },
+    /// The first of the `errors` the server answered with. Older servers report queries
+    /// using fields they do not have this way too.
+    #[error("{0}")]
+    Query(String),
+}
+
+/// Runs `query` against the GraphQL endpoint at `url` and returns its `data`.
+pub fn graphql(
+    url: &str,
+    query: &str,
+    variables: Option<Value>,
+    timeout: Duration,
+) -> Result<Value, GraphqlError> {
+    let mut body = serde_json::json!({ "query": query });
+    if let Some(variables) = variables {
+        body["variables"] = variables;
+    }
+    let response = post_json(url, &body.to_string(), timeout).ok_or(GraphqlError::Unreachable)?;
+    if response.status == 404 {
+        return Err(GraphqlError::NotFound);
+    }
+
+    let mut value: Value =
+        serde_json::from_slice(&response.body).map_err(|_| GraphqlError::InvalidResponse {
+            status: response.status,
+        })?;
+    if let Some(error) = value.pointer("/errors/0") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        return Err(GraphqlError::Query(message.to_string()));
+    }
+    Ok(value["data"].take())
+}
+
+/// Streams the (uncompressed) body of `url` into `out`, following redirects, without the
+/// size cap applied to [`get`]. Any non-2xx status is an error.
+pub fn download(url: &str, timeout: Duration, out: &mut impl Write) -> Result<u64, String> {
+    let response = agent_for(url)
+        .get(url)
+        .timeout(timeout)
+        .call()
+        .map_err(|err| err.to_string())?;
+
+    std::io::copy(&mut response.into_reader(), out).map_err(|err| err.to_string())
+}
+
+fn send(
+    url: &str,
+    timeout: Duration,
+    host_header: Option<&str>,
+    authorization: Option<&str>,
+    json_body: Option<&str>,
+) -> Option<HttpResponse> {
+    let agent = agent_for(url);
+    let mut request = match json_body {
+        Some(_) => agent.post(url),
+        None => agent.get(url),
+    }
+    .timeout(timeout)
+    .set("Accept-Encoding", "gzip, deflate");
+
+    if let Some(host) = host_header {
+        request = request.set("Host", host);
+    }
+    if let Some(authorization) = authorization {
+        request = request.set("Authorization", authorization);
+    }
+
+    let result = match json_body {
+        Some(body) => request
+            .set("Content-Type", "application/json")
+            .send_string(body),
+        None => request.call(),
+    };
+
+    read_response(result)
+}
+
+/// POSTs `length` bytes read from `body` to `url` without holding them in memory, for
+/// uploads too large for [`post_json`]. Same semantics as [`get`].
+pub fn post_stream(
+    url: &str,
+    content_type: &str,
+    length: u64,
+    body: impl Read,
+    timeout: Duration,
+) -> Option<HttpResponse> {
+    let mut request = agent_for(url)
+        .post(url)
+        .timeout(timeout)
+        .set("Accept-Encoding", "gzip, deflate")
+        .set("Content-Type", content_type)
+        // Announced up front, since not every server accepts a chunked upload.
+        .set("Content-Length", &length.to_string());
+
+    if let Some(host) = host_header() {
+        request = request.set("Host", &host);
+    }
+    if let Some(authorization) = AUTHORIZATION.as_deref() {
+        request = request.set("Authorization", authorization);
+    }
+
+    read_response(request.send(body))
+}
+
+fn read_response(result: Result<ureq::Response, ureq::Error>) -> Option<HttpResponse> {
+    let response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(_) => return None,
+    };
+
+    let status = response.status();
+    let encoding = response
+        .header("Content-Encoding")
+        .map(|value| value.trim().to_ascii_lowercase());
+
+    let mut raw = Vec::new();
+    let body = match response
+        .into_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_end(&mut raw)
+    {
+        // A body we cannot read or decode still leaves the status usable for health checks.
+        Ok(_) => decode_body(encoding.as_deref(), raw).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+
+    Some(HttpResponse { status, body })
+}
+
+fn host_header() -> Option<String> {
+    env::var("SUWAYOMI_HOST_HEADER")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn authorization() -> Result<Option<String>, String> {
+    if let Some(api_key) = secret("SUWAYOMI_API_KEY")? {
+        return Ok(Some(format!("Bearer {api_key}")));
+    }
+
+    let Some(password) = secret("SUWAYOMI_PASSWORD")? else {
+        return Ok(None);
+    };
+    let username = env::var("SUWAYOMI_USERNAME").unwrap_or_default();
+    Ok(Some(format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{password}", username.trim()))
+    )))
+}
+
+/// `name` from the environment, or from the file `<name>_FILE` points at.
+fn secret(name: &str) -> Result<Option<String>, String> {
+    let file_var = format!("{name}_FILE");
+    secret_from(&file_var, env::var_os(&file_var), env::var(name).ok())
+}
+
+/// `inline`, unless `file` names a file to read it from instead. `file_var` names where
+/// `file` came from in errors.
+pub(crate) fn secret_from(
+    file_var: &str,
+    file: Option<OsString>,
+    inline: Option<String>,
+) -> Result<Option<String>, String> {
+    let Some(path) = file.filter(|path| !path.is_empty()) else {
+        return Ok(inline
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty()));
+    };
+
+    let path = Path::new(&path);
+    let contents = fs::read_to_string(path).map_err(|err| {
+        format!(
+            "{file_var} points at {}, which cannot be read: {err}",
+            path.display()
+        )
+    })?;
+    let value = contents.trim();
+    if value.is_empty() {
+        return Err(format!(
+            "{file_var} points at {}, which is empty",
+            path.display()
+        ));
+    }
+    Ok(Some(value.to_string()))
+}
+
+fn agent_for(url: &str) -> &'static ureq::Agent {
+    let insecure_override = cli::env_flag("SUWAYOMI_INSECURE_TLS");
+
+    match Url::parse(url) {
+        Ok(parsed) if allows_insecure_tls(&parsed, insecure_override) => &RELAXED_AGENT,
+        _ => &VERIFYING_AGENT,
+    }
+}
+
+/// Local servers typically use self-signed certificates, so only remote hosts are held to
+/// full verification unless the user opts out with `SUWAYOMI_INSECURE_TLS=1`.
+fn allows_insecure_tls(url: &Url, insecure_override: bool) -> bool {
+    insecure_override || is_loopback(url)
+}
+
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+fn insecure_tls_config() -> rustls::ClientConfig {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .expect("ring provider supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth()
+}
+
+/// Skips chain and hostname validation but still checks handshake signatures.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn decode_body(encoding: Option<&str>, raw: Vec<u8>) -> Option<Vec<u8>> {
+    match encoding {
+        None | Some("") | Some("identity") => Some(raw),
+        Some("gzip") | Some("x-gzip") => read_all(GzDecoder::new(raw.as_slice())),
+        // "deflate" is meant to be zlib-wrapped, but plenty of servers send a raw stream.
+        Some("deflate") => read_all(ZlibDecoder::new(raw.as_slice()))
+            .or_else(|| read_all(DeflateDecoder::new(raw.as_slice()))),
+        Some(_) => None,
+    }
+}
+
+fn read_all(reader: impl Read) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    reader.take(MAX_BODY_BYTES).read_to_end(&mut decoded).ok()?;
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{respond, serve};
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    const BODY: &[u8] = br#"{"name":"Suwayomi-Server","version":"v2.0.0"}"#;
+
+    #[test]
+    fn get_decodes_gzip_body() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(BODY).expect("compress body");
+        let compressed = encoder.finish().expect("finish gzip");
+
+        let response =
+            get(&serve_once("gzip", compressed), Duration::from_secs(2)).expect("response");
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, BODY);
+    }
+
+    #[test]
+    fn get_decodes_zlib_and_raw_deflate_bodies() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(BODY).expect("compress body");
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(BODY).expect("compress body");
+
+        for compressed in [
+            zlib.finish().expect("finish zlib"),
+            raw.finish().expect("finish deflate"),
+        ] {
+            let url = serve_once("deflate", compressed);
+            let response = get(&url, Duration::from_secs(2)).expect("response");
+            assert_eq!(response.body, BODY);
+        }
+    }
+
+    #[test]
+    fn decode_body_rejects_unknown_encoding() {
+        assert!(decode_body(Some("br"), BODY.to_vec()).is_none());
+        assert_eq!(decode_body(None, BODY.to_vec()).as_deref(), Some(BODY));
+    }
+
+    #[test]
+    fn send_sets_configured_host_header() {
+        let base_url = serve(2, |request| {
+            if request.contains("\r\nHost: manga.example.com\r\n") {
+                respond(200, "{}")
+            } else {
+                respond(421, "")
+            }
+        });
+
+        let without = send(&base_url, Duration::from_secs(2), None, None, None).expect("response");
+        let with = send(
+            &base_url,
+            Duration::from_secs(2),
+            Some("manga.example.com"),
+            None,
+            None,
+        )
+        .expect("response");
+
+        assert_eq!(without.status, 421);
+        assert_eq!(with.status, 200);
+    }
+
+    #[test]
+    fn secrets_are_read_from_files_first() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let token = dir.path().join("api-key");
+        std::fs::write(&token, "s3cret-token\n").expect("write token");
+
+        assert_eq!(
+            secret_from("KEY_FILE", Some(token.into()), Some("inline".into())),
+            Ok(Some("s3cret-token".to_string()))
+        );
+        assert_eq!(
+            secret_from("KEY_FILE", None, Some(" inline ".into())),
+            Ok(Some("inline".to_string()))
+        );
+
+        let missing = secret_from("KEY_FILE", Some(dir.path().join("missing").into()), None)
+            .expect_err("missing file");
+        assert!(missing.starts_with("KEY_FILE points at "), "{missing}");
+    }
+
+    #[test]
+    fn send_sets_authorization_header() {
+        let base_url = serve(1, |request| {
+            if request.contains("\r\nAuthorization: Bearer s3cret\r\n") {
+                respond(200, "{}")
+            } else {
+                respond(401, "")
+            }
+        });
+
+        let response = send(
+            &base_url,
+            Duration::from_secs(2),
+            None,
+            Some("Bearer s3cret"),
+            None,
+        )
+        .expect("response");
+
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn post_json_sends_json_content_type() {
+        let base_url = serve(1, |request| {
+            if request.starts_with("POST ")
+                && request.contains("\r\nContent-Type: application/json\r\n")
+            {
+                respond(200, "{}")
+            } else {
+                respond(400, "")
+            }
+        });
+
+        let response = post_json(&base_url, "{}", Duration::from_secs(2)).expect("response");
+
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn graphql_returns_data_or_the_reason_there_is_none() {
+        let timeout = Duration::from_secs(2);
+        let base_url = serve(4, |request| {
+            if request.contains("\"variables\":{\"id\":\"1\"}") {
+                respond(200, r#"{"data":{"restoreStatus":{"state":"SUCCESS"}}}"#)
+            } else if request.contains("missing") {
+                respond(
+                    200,
+                    r#"{"errors":[{"message":"Field 'missing' is undefined"}]}"#,
+                )
+            } else if request.contains("legacy") {
+                respond(404, "")
+            } else {
+                respond(502, "Bad Gateway")
+            }
+        });
+
+        let variables = serde_json::json!({ "id": "1" });
+        let data = graphql(
+            &base_url,
+            "query ($id: String!) { x }",
+            Some(variables),
+            timeout,
+        );
+        assert_eq!(data.expect("data")["restoreStatus"]["state"], "SUCCESS");
+        assert_eq!(
+            graphql(&base_url, "{ missing }", None, timeout),
+            Err(GraphqlError::Query(
+                "Field 'missing' is undefined".to_string()
+            ))
+        );
+        assert_eq!(
+            graphql(&base_url, "{ legacy }", None, timeout),
+            Err(GraphqlError::NotFound)
+        );
+        assert_eq!(
+            graphql(&base_url, "{ proxied }", None, timeout),
+            Err(GraphqlError::InvalidResponse { status: 502 })
+        );
+    }
+
+    #[test]
+    fn post_stream_announces_the_length_of_the_body() {
+        let base_url = serve(1, |request| {
+            if request.contains("\r\nContent-Length: 5\r\n") && request.ends_with("\r\n\r\nhello") {
+                respond(200, "{}")
+            } else {
+                respond(400, "")
+            }
+        });
+
+        let response = post_stream(
+            &base_url,
+            "application/octet-stream",
+            5,
+            &b"hello"[..],
+            Duration::from_secs(2),
+        )
+        .expect("response");
+
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn tls_verification_is_relaxed_only_for_loopback_or_override() {
+        let parse = |url: &str| Url::parse(url).expect("valid url");
+
+        assert!(allows_insecure_tls(&parse("https://127.0.0.1:4567"), false));
+        assert!(allows_insecure_tls(&parse("https://localhost:4567"), false));
+        assert!(allows_insecure_tls(&parse("https://[::1]:4567"), false));
+        assert!(!allows_insecure_tls(
+            &parse("https://manga.example.com"),
+            false
+        ));
+        assert!(!allows_insecure_tls(
+            &parse("https://192.168.1.20:4567"),
+            false
+        ));
+        assert!(allows_insecure_tls(
+            &parse("https://manga.example.com"),
+            true
+        ));
+    }
+
+    #[test]
+    fn insecure_tls_config_builds() {
+        let config = insecure_tls_config();
+        assert!(config.alpn_protocols.is_empty());
+    }
+
+    fn serve_once(encoding: &'static str, body: Vec<u8>) -> String {
+        serve(1, move |_| {
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Encoding: {encoding}\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .into_bytes();
+            response.extend_from_slice(&body);
+            response
+        })
+    }
+}
+
+/// Minimal scripted HTTP server for tests that need to observe or vary responses.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves `connections` requests on a loopback port, answering each with `handler`'s
+    /// raw response bytes. Returns the base URL (without a trailing slash).
+    pub fn serve<F>(connections: usize, handler: F) -> String
+    where
+        F: Fn(&str) -> Vec<u8> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind test listener");
+        let port = listener.local_addr().expect("listener addr").port();
+
+        std::thread::spawn(move || {
+            for _ in 0..connections {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+
+                let request = read_request(&mut stream);
+                let _ = stream.write_all(&handler(&request));
+            }
+        });
+
+        format!("http://127.0.0.1:{port}")
+    }
+
+    pub fn respond(status: u16, body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .into_bytes()
+    }
+
+    fn read_request(stream: &mut impl Read) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0_u8; 1024];
+
+        // Headers, then as much body as they announce, so handlers can look at both.
+        let mut expected = None;
+        while expected.is_none_or(|expected| request.len() < expected) {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => request.extend_from_slice(&buffer[..read]),
+            }
+            if expected.is_none() {
+                expected = request
+                    .windows(4)
+                    .position(|window| window == b"\r\n\r\n")
+                    .map(|end| end + 4 + content_length(&request[..end]));
+            }
+        }
+
+        String::from_utf8_lossy(&request).into_owned()
+    }
+
+    fn content_length(headers: &[u8]) -> usize {
+        String::from_utf8_lossy(headers)
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.trim()
+                    .eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse().ok())?
+            })
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,197 @@
+use crate::http::{self, GraphqlError};
+use crate::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Large libraries on slow sources take a while; past this the update keeps running on the
+/// server and the app just stops following it.
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Status checks in a row that may go unanswered, say while a proxy restarts, before the
+/// update is given up on.
+const MAX_POLL_FAILURES: u32 = 5;
+const UPDATE_MUTATION: &str = "mutation { updateLibrary(input: {}) { updateStatus { jobsInfo \
+     { isRunning totalJobs finishedJobs } } } }";
+const STATUS_QUERY: &str =
+    "query { libraryUpdateStatus { jobsInfo { isRunning totalJobs finishedJobs } } }";
+const UNSUPPORTED: &str = "this server has no API for updating the library";
+
+/// Set while an update is followed, from the tray or the `update_library` command, so the
+/// two never start one on top of the other.
+static UPDATE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    /// Manga checked for new chapters so far.
+    pub finished: u64,
+    pub total: u64,
+}
+
+/// Makes the connected server check every manga in the library for new chapters and waits
+/// for it to finish, reporting progress as manga are done.
+pub fn update(progress: impl FnMut(UpdateProgress)) -> Result<(), String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    if UPDATE_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("the library is already being updated".to_string());
+    }
+    let _running = RunningUpdate;
+    update_at(base_url.trim_end_matches('/'), progress)
+}
+
+/// Clears `UPDATE_RUNNING` when the update ends, however it ends.
+struct RunningUpdate;
+
+impl Drop for RunningUpdate {
+    fn drop(&mut self) {
+        UPDATE_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+fn update_at(base_url: &str, mut progress: impl FnMut(UpdateProgress)) -> Result<(), String> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let started = match http::graphql(&url, UPDATE_MUTATION, None, REQUEST_TIMEOUT) {
+        Ok(started) => started,
+        // Servers from before the GraphQL API, or from before the mutation.
+        Err(GraphqlError::NotFound) => return Err(UNSUPPORTED.to_string()),
+        Err(GraphqlError::Query(message)) if message.contains("updateLibrary") => {
+            return Err(UNSUPPORTED.to_string())
+        }
+        Err(err) => return Err(format!("updating the library failed: {err}")),
+    };
+    let Some(mut jobs) = started
+        .pointer("/updateLibrary/updateStatus/jobsInfo")
+        .cloned()
+    else {
+        return Err("updating the library failed: the server did not start it".to_string());
+    };
+
+    let deadline = Instant::now() + UPDATE_TIMEOUT;
+    let mut reported = None;
+    let mut failures = 0;
+    loop {
+        let count = |key| jobs.get(key).and_then(Value::as_u64).unwrap_or(0);
+        let current = UpdateProgress {
+            finished: count("finishedJobs"),
+            total: count("totalJobs"),
+        };
+        if reported != Some(current) {
+            reported = Some(current);
+            progress(current);
+        }
+
+        if jobs.get("isRunning").and_then(Value::as_bool) != Some(true) {
+            break;
+        }
+        if Instant::now() >= deadline {
+            return Err("the server is still updating the library; check on it later".to_string());
+        }
+        thread::sleep(STATUS_POLL_INTERVAL);
+        let status = match http::graphql(&url, STATUS_QUERY, None, REQUEST_TIMEOUT) {
+            Ok(status) => status,
+            Err(err @ (GraphqlError::Unreachable | GraphqlError::InvalidResponse { .. }))
+                if failures < MAX_POLL_FAILURES =>
+            {
+                failures += 1;
+                eprintln!("checking on the library update failed, trying again: {err}");
+                continue;
+            }
+            Err(err) => return Err(format!("following the library update failed: {err}")),
+        };
+        failures = 0;
+        let Some(next) = status.pointer("/libraryUpdateStatus/jobsInfo") else {
+            return Err("the server stopped reporting on the library update".to_string());
+        };
+        jobs = next.clone();
+    }
+
+    eprintln!("library update on {base_url} finished");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    #[test]
+    fn update_is_followed_until_the_server_is_done() {
+        let base_url = serve(2, |request| {
+            if request.contains("updateLibrary") {
+                respond(
+                    200,
+                    r#"{"data":{"updateLibrary":{"updateStatus":{"jobsInfo":{"isRunning":true,"totalJobs":3,"finishedJobs":1}}}}}"#,
+                )
+            } else {
+                respond(
+                    200,
+                    r#"{"data":{"libraryUpdateStatus":{"jobsInfo":{"isRunning":false,"totalJobs":3,"finishedJobs":3}}}}"#,
+                )
+            }
+        });
+
+        let mut reported = Vec::new();
+        update_at(&base_url, |progress| reported.push(progress)).expect("update");
+        assert_eq!(
+            reported,
+            [
+                UpdateProgress {
+                    finished: 1,
+                    total: 3,
+                },
+                UpdateProgress {
+                    finished: 3,
+                    total: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unanswered_checks_are_retried_but_a_missing_status_is_an_error() {
+        use std::sync::atomic::AtomicUsize;
+
+        let started = r#"{"data":{"updateLibrary":{"updateStatus":{"jobsInfo":{"isRunning":true,"totalJobs":2,"finishedJobs":0}}}}}"#;
+        let polls = AtomicUsize::new(0);
+        let flaky = serve(3, move |request| {
+            if request.contains("updateLibrary") {
+                respond(200, started)
+            } else if polls.fetch_add(1, Ordering::SeqCst) == 0 {
+                respond(502, "Bad Gateway")
+            } else {
+                respond(
+                    200,
+                    r#"{"data":{"libraryUpdateStatus":{"jobsInfo":{"isRunning":false,"totalJobs":2,"finishedJobs":2}}}}"#,
+                )
+            }
+        });
+        assert_eq!(update_at(&flaky, drop), Ok(()));
+
+        let forgetful = serve(2, move |request| {
+            if request.contains("updateLibrary") {
+                respond(200, started)
+            } else {
+                respond(200, r#"{"data":{"libraryUpdateStatus":null}}"#)
+            }
+        });
+        assert!(update_at(&forgetful, drop).is_err());
+    }
+
+    #[test]
+    fn servers_without_the_mutation_are_unsupported() {
+        let old = serve(1, |_| {
+            respond(
+                200,
+                r#"{"errors":[{"message":"Validation error: Field 'updateLibrary' in type 'Mutation' is undefined"}]}"#,
+            )
+        });
+        assert_eq!(update_at(&old, drop), Err(UNSUPPORTED.to_string()));
+
+        let legacy = serve(1, |_| respond(404, ""));
+        assert_eq!(update_at(&legacy, drop), Err(UNSUPPORTED.to_string()));
+    }
+}
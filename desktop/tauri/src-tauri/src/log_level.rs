@@ -0,0 +1,94 @@
+use crate::server_settings::{self, Applied, Setting};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const DEBUG_LOGS: Setting = Setting {
+    field: "debugLogsEnabled",
+    conf_key: "server.debugLogsEnabled",
+    description: "log level",
+};
+
+/// Levels accepted from the UI. The server itself only distinguishes normal from debug
+/// logging, so `debug` and `trace` turn debug logs on and the rest turn them off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn debug_logs(self) -> bool {
+        matches!(self, Self::Debug | Self::Trace)
+    }
+}
+
+/// The launcher's own level, from `SUWAYOMI_LAUNCHER_LOG_LEVEL`, `info` when it is unset or
+/// not a level.
+static LAUNCHER_LOG_LEVEL: Lazy<LogLevel> = Lazy::new(|| {
+    std::env::var("SUWAYOMI_LAUNCHER_LOG_LEVEL")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(LogLevel::Info)
+});
+
+/// Whether the launcher writes its debug output, like how long each health probe took.
+pub(crate) fn launcher_debug() -> bool {
+    LAUNCHER_LOG_LEVEL.debug_logs()
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" | "warning" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            other => Err(format!(
+                "unknown log level {other:?}; expected error, warn, info, debug or trace"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLevelChange {
+    pub debug_logs_enabled: bool,
+    pub applied: Applied,
+    /// Set after a restart, since the server may come back at a different URL.
+    pub base_url: Option<String>,
+}
+
+/// Turns the server's debug logging on or off for `level`, live when the server has a
+/// settings API and through `server.conf` otherwise.
+pub fn set(level: &str, resource_dir: Option<PathBuf>) -> Result<LogLevelChange, String> {
+    let debug_logs_enabled = level.parse::<LogLevel>()?.debug_logs();
+    let change = server_settings::change(&DEBUG_LOGS, debug_logs_enabled.into(), resource_dir)?;
+    Ok(LogLevelChange {
+        debug_logs_enabled,
+        applied: change.applied,
+        base_url: change.base_url,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_levels_case_insensitively() {
+        assert_eq!("DEBUG".parse(), Ok(LogLevel::Debug));
+        assert_eq!(" warning ".parse(), Ok(LogLevel::Warn));
+        assert!("verbose".parse::<LogLevel>().is_err());
+        assert!(LogLevel::Trace.debug_logs());
+        assert!(!LogLevel::Info.debug_logs());
+    }
+}
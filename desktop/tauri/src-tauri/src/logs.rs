@@ -0,0 +1,110 @@
+use crate::events::{self, now_ms};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Enough for a startup stack trace or a few minutes of chatter; older lines are dropped.
+const SERVER_LOG_CAPACITY: usize = 2000;
+
+static SERVER_LOG: Lazy<Mutex<VecDeque<LogLine>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(SERVER_LOG_CAPACITY)));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogSource {
+    /// A launcher lifecycle event.
+    Launcher,
+    /// A line the managed server wrote to stdout or stderr.
+    Server,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub source: LogSource,
+    pub text: String,
+}
+
+/// Keeps a line of managed server output for `combined`.
+pub fn record_server_line(line: &str) {
+    let line = LogLine {
+        timestamp_ms: now_ms(),
+        source: LogSource::Server,
+        text: line.trim_end_matches(['\r', '\n']).to_string(),
+    };
+
+    let mut log = SERVER_LOG.lock().expect("server log mutex poisoned");
+    if log.len() == SERVER_LOG_CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(line);
+}
+
+/// Launcher events and server output interleaved in the order they happened, oldest first.
+pub fn combined() -> Vec<LogLine> {
+    let launcher = events::recent()
+        .into_iter()
+        .map(|event| LogLine {
+            timestamp_ms: event.timestamp_ms,
+            source: LogSource::Launcher,
+            text: event.message,
+        })
+        .collect();
+    let server = SERVER_LOG
+        .lock()
+        .expect("server log mutex poisoned")
+        .iter()
+        .cloned()
+        .collect();
+    merge(launcher, server)
+}
+
+/// Both inputs are already in order; on equal timestamps the launcher line comes first.
+fn merge(launcher: Vec<LogLine>, server: Vec<LogLine>) -> Vec<LogLine> {
+    let mut lines: Vec<LogLine> = launcher.into_iter().chain(server).collect();
+    lines.sort_by_key(|line| line.timestamp_ms);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(timestamp_ms: u64, source: LogSource, text: &str) -> LogLine {
+        LogLine {
+            timestamp_ms,
+            source,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn launcher_and_server_lines_are_interleaved_by_time() {
+        let launcher = vec![
+            line(10, LogSource::Launcher, "server process started"),
+            line(30, LogSource::Launcher, "server is ready"),
+        ];
+        let server = vec![
+            line(10, LogSource::Server, "Starting Suwayomi"),
+            line(20, LogSource::Server, "Loading extensions"),
+            line(40, LogSource::Server, "Javalin started"),
+        ];
+
+        let texts: Vec<_> = merge(launcher, server)
+            .into_iter()
+            .map(|line| line.text)
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "server process started",
+                "Starting Suwayomi",
+                "Loading extensions",
+                "server is ready",
+                "Javalin started",
+            ]
+        );
+    }
+}
@@ -1,35 +1,356 @@
+mod about;
+mod backup;
+mod capture;
+mod cli;
+mod commands;
+mod downloads;
+mod events;
+#[cfg(all(test, feature = "test-fake-server"))]
+mod fake_server;
+mod http;
 mod launcher;
+mod library;
+mod log_level;
+mod logs;
+mod migrations;
+mod navigation;
+mod pause;
+mod server_settings;
+mod sources;
+mod supervisor;
+mod system_check;
+mod tray;
+mod updates;
+mod usage;
 
-use tauri::{Manager, RunEvent, WebviewUrl, WebviewWindowBuilder};
+use launcher::WindowCloseBehavior;
+use supervisor::ConnectionChange;
+use tauri::window::Color;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 
 fn main() {
+    if cli::options().print_config {
+        print!("{}", launcher::config_report());
+        return;
+    }
+
+    let context = tauri::generate_context!();
+    if cli::options().dry_run {
+        let resource_dir =
+            tauri::utils::platform::resource_dir(context.package_info(), &tauri::Env::default());
+        match launcher::dry_run(resource_dir.ok()) {
+            Ok(report) => print!("{report}"),
+            Err(err) => {
+                eprintln!("the server could not be started: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if cli::options().reset_database {
+        if let Err(err) = launcher::reset_database(cli::options().confirm) {
+            eprintln!("not resetting the database: {err}");
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(any(unix, windows))]
+    if WindowCloseBehavior::from_env() == WindowCloseBehavior::KeepServerUntilLogout {
+        launcher::stop_server_on_session_end();
+    }
+
     let app = tauri::Builder::default()
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
         .setup(move |app| {
-            let base_url = match launcher::bootstrap(app.path().resource_dir().ok()) {
-                Ok(bootstrap) => bootstrap.base_url,
-                Err(err) => {
-                    eprintln!("launcher bootstrap failed: {err}");
-                    launcher::fallback_base_url()
-                }
-            };
-            let external_url = url::Url::parse(&base_url)?;
+            // Checked before anything is spawned, since without a web view there is no UI.
+            #[cfg(windows)]
+            if !webview2_installed() {
+                prompt_webview2_install(app.handle());
+                return Ok(());
+            }
 
-            if let Some(window) = app.get_webview_window("main") {
-                window.navigate(external_url)?;
-                window.set_title("Suwayomi")?;
+            let resource_dir = app.path().resource_dir().ok();
+            // Cosmetic: the app works without a tray icon, only close-to-tray does not.
+            if let Err(err) = tray::create(app) {
+                eprintln!("failed to create the tray icon, continuing without it: {err}");
+            }
+
+            let window = if cli::options().control_panel {
+                let window =
+                    WebviewWindowBuilder::new(app, "main", WebviewUrl::App("control.html".into()))
+                        .title("Suwayomi Server")
+                        .inner_size(480.0, 640.0)
+                        .background_color(window_background())
+                        .build();
+
+                // The panel polls the status itself, so startup must not hold up the window.
+                if window.is_ok() {
+                    let handle = app.handle().clone();
+                    let startup_dir = resource_dir.clone();
+                    std::thread::spawn(move || match launcher::bootstrap(startup_dir) {
+                        Ok(bootstrap) => tray::show_base_url(&handle, &bootstrap.base_url),
+                        Err(err) => eprintln!("launcher bootstrap failed: {err}"),
+                    });
+                }
+                window
             } else {
+                let base_url = match launcher::bootstrap(resource_dir.clone()) {
+                    Ok(bootstrap) => bootstrap.base_url,
+                    Err(err) => {
+                        eprintln!("launcher bootstrap failed: {err}");
+                        launcher::fallback_base_url()
+                    }
+                };
+                let external_url = url::Url::parse(&base_url)?;
+
+                tray::show_base_url(app.handle(), &base_url);
+                let extra_origins = navigation::extra_origins();
                 WebviewWindowBuilder::new(app, "main", WebviewUrl::External(external_url))
                     .title("Suwayomi")
-                    .build()?;
+                    .inner_size(1280.0, 800.0)
+                    .background_color(window_background())
+                    .on_navigation(move |url| {
+                        // The server can move (restart, connect_to); follow it.
+                        let current = launcher::active_base_url();
+                        let server = current.as_deref().unwrap_or(&base_url);
+                        if navigation::is_allowed(url, server, &extra_origins) {
+                            return true;
+                        }
+                        if !navigation::opens_externally(url) {
+                            eprintln!("blocked navigation to a {}: link", url.scheme());
+                            return false;
+                        }
+                        if let Err(err) = navigation::open_externally(url.as_str()) {
+                            eprintln!("failed to open {url}: {err}");
+                        }
+                        false
+                    })
+                    .build()
+            };
+            if let Err(err) = window {
+                report_webview_failure(app.handle(), &err);
+                return Ok(());
             }
+
+            let handle = app.handle().clone();
+            supervisor::monitor_connection(move |change, base_url| {
+                let event = match change {
+                    ConnectionChange::Lost => "connection-lost",
+                    ConnectionChange::Restored => "connection-restored",
+                };
+                let _ = handle.emit(event, base_url);
+            });
+
+            let handle = app.handle().clone();
+            supervisor::spawn(resource_dir, move |base_url| {
+                let _ = commands::navigate_main_window(&handle, base_url);
+            });
             Ok(())
         })
-        .build(tauri::generate_context!())
+        .invoke_handler(tauri::generate_handler![
+            commands::start_server,
+            commands::restart_server,
+            commands::stop_server,
+            commands::rediscover,
+            commands::connect_to,
+            commands::switch_server,
+            commands::probe_url,
+            commands::reload_config,
+            commands::refresh_sources,
+            commands::check_repos,
+            commands::pause_server,
+            commands::reveal_in_file_manager,
+            commands::capture_page,
+            commands::import_backup,
+            commands::update_library,
+            commands::set_server_log_level,
+            commands::get_download_concurrency,
+            commands::set_download_concurrency,
+            commands::reset_app_state,
+            commands::recent_events,
+            commands::combined_logs,
+            commands::confirm_quit,
+            commands::quit_prompt_shown,
+            commands::answer_quit_prompt,
+            commands::quit_app,
+            commands::relaunch_app,
+            commands::effective_config,
+            commands::runtime_settings,
+            commands::get_reconnect_policy,
+            commands::set_reconnect_policy,
+            commands::server_about,
+            commands::show_about,
+            commands::stage_server_update,
+            commands::server_status,
+            commands::bootstrap_error,
+            commands::previous_failure,
+            commands::data_dir_usage,
+            commands::system_check,
+            commands::copy_server_url,
+        ])
+        .build(context)
         .expect("failed to build Tauri application");
 
-    app.run(|_, event| {
-        if matches!(event, RunEvent::Exit | RunEvent::ExitRequested { .. }) {
-            launcher::shutdown_child_process();
+    app.run(|app, event| match event {
+        RunEvent::WindowEvent {
+            label,
+            event: WindowEvent::CloseRequested { api, .. },
+            ..
+        } if label == "main" => {
+            match WindowCloseBehavior::from_env().effective(tray::exists(app)) {
+                WindowCloseBehavior::Tray => {
+                    api.prevent_close();
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.hide();
+                    }
+                }
+                // The app stays alive, out of sight, to stop the server when the session ends.
+                WindowCloseBehavior::KeepServerUntilLogout => {
+                    api.prevent_close();
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.hide();
+                    }
+                }
+                WindowCloseBehavior::KeepServer => launcher::detach_child_process(),
+                WindowCloseBehavior::Quit => {
+                    if commands::hold_exit_for_confirmation(app) {
+                        api.prevent_close();
+                    }
+                }
+            }
+        }
+        // Coming back to the app, often after sleep: check on the server now rather than at
+        // the next poll, in case the OS killed it meanwhile.
+        RunEvent::WindowEvent {
+            label,
+            event: WindowEvent::Focused(true),
+            ..
+        } if label == "main" => supervisor::wake(),
+        RunEvent::ExitRequested { api, .. } => {
+            if commands::hold_exit_for_confirmation(app) {
+                api.prevent_exit();
+            } else {
+                launcher::shutdown_child_process();
+            }
         }
+        RunEvent::Exit => launcher::shutdown_child_process(),
+        _ => {}
     });
 }
+
+/// Painted until the web UI renders. Dark like the UI itself, so startup does not flash
+/// white.
+const DEFAULT_WINDOW_BACKGROUND: Color = Color(0x12, 0x12, 0x12, 0xff);
+
+/// `SUWAYOMI_BG_COLOR` as `#rrggbb` or `#rrggbbaa`, for light themes or custom web UIs.
+fn window_background() -> Color {
+    let Ok(raw) = std::env::var("SUWAYOMI_BG_COLOR") else {
+        return DEFAULT_WINDOW_BACKGROUND;
+    };
+    raw.trim().parse().unwrap_or_else(|err| {
+        eprintln!("ignoring invalid SUWAYOMI_BG_COLOR {raw:?}: {err}");
+        DEFAULT_WINDOW_BACKGROUND
+    })
+}
+
+/// Without a window there is nothing to use the server from, so stop it rather than leave
+/// the JVM orphaned, and explain why the app is about to quit. Setup returns normally so
+/// the event loop runs long enough to show the dialog.
+fn report_webview_failure(app: &AppHandle, err: &tauri::Error) {
+    eprintln!("failed to create the main window: {err}");
+    launcher::shutdown_child_process();
+
+    let webview_hint = if cfg!(windows) {
+        "Make sure the Microsoft Edge WebView2 Runtime is installed."
+    } else if cfg!(target_os = "linux") {
+        "Make sure WebKitGTK is installed."
+    } else {
+        "Make sure your system is up to date."
+    };
+    let handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "Suwayomi could not open its window because the system web view failed to \
+             start.\n\n{webview_hint}\n\nDetails: {err}"
+        ))
+        .title("Suwayomi cannot start")
+        .kind(MessageDialogKind::Error)
+        .show(move |_| handle.exit(1));
+}
+
+/// WebView2 registers itself with EdgeUpdate under this client ID, per machine (32- or
+/// 64-bit view) or per user.
+#[cfg(windows)]
+const WEBVIEW2_CLIENT_ID: &str = "{F3017226-FE2A-4295-8BDF-00C3A9A7E4C5}";
+#[cfg(windows)]
+const EDGE_UPDATE_CLIENT_KEYS: &[&str] = &[
+    r"HKLM\SOFTWARE\WOW6432Node\Microsoft\EdgeUpdate\Clients",
+    r"HKLM\SOFTWARE\Microsoft\EdgeUpdate\Clients",
+    r"HKCU\Software\Microsoft\EdgeUpdate\Clients",
+];
+#[cfg(windows)]
+const WEBVIEW2_DOWNLOAD_URL: &str = "https://go.microsoft.com/fwlink/p/?LinkId=2124703";
+
+/// Whether any registration has a real version (`pv`); uninstalling can leave `0.0.0.0`.
+#[cfg(windows)]
+fn webview2_installed() -> bool {
+    use launcher::CREATE_NO_WINDOW;
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    EDGE_UPDATE_CLIENT_KEYS.iter().any(|clients| {
+        let Ok(output) = Command::new("reg")
+            .args([
+                "query",
+                &format!(r"{clients}\{WEBVIEW2_CLIENT_ID}"),
+                "/v",
+                "pv",
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+        else {
+            // Without `reg` there is no telling; let window creation report a real failure.
+            return true;
+        };
+
+        String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("pv")
+                && fields.next() == Some("REG_SZ")
+                && fields.next().is_some_and(|version| version != "0.0.0.0")
+        })
+    })
+}
+
+/// Offers the WebView2 bootstrapper download, then quits: the app cannot show anything
+/// until the runtime is installed.
+#[cfg(windows)]
+fn prompt_webview2_install(app: &AppHandle) {
+    use tauri_plugin_dialog::MessageDialogButtons;
+
+    eprintln!("Microsoft Edge WebView2 Runtime is not installed");
+    let handle = app.clone();
+    app.dialog()
+        .message(
+            "Suwayomi needs the Microsoft Edge WebView2 Runtime, which is not installed on \
+             this computer.\n\nDownload it from Microsoft, run the installer and start \
+             Suwayomi again.",
+        )
+        .title("WebView2 Runtime required")
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Download".to_string(),
+            "Quit".to_string(),
+        ))
+        .show(move |download| {
+            if download {
+                if let Err(err) = navigation::open_externally(WEBVIEW2_DOWNLOAD_URL) {
+                    eprintln!("failed to open {WEBVIEW2_DOWNLOAD_URL}: {err}");
+                }
+            }
+            handle.exit(1);
+        });
+}
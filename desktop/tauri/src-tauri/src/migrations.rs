@@ -0,0 +1,403 @@
+use crate::launcher;
+use flate2::read::DeflateDecoder;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const MANIFEST_ENTRY: &str = "META-INF/MANIFEST.MF";
+const MAX_MANIFEST_BYTES: u64 = 64 * 1024;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_RECORD_SIGNATURE: u32 = 0x0605_4b50;
+const END_RECORD_LEN: usize = 22;
+/// The end record is followed by an archive comment of at most this many bytes.
+const MAX_ZIP_COMMENT_LEN: usize = 0xffff;
+/// The server jar's directory is well under this; anything bigger is not a jar we ship.
+const MAX_CENTRAL_DIRECTORY_BYTES: u64 = 16 * 1024 * 1024;
+/// The last started server version, a section of the launcher state file.
+const VERSION_STATE_KEY: &str = "serverVersion";
+
+/// Where a migration may touch data.
+pub struct MigrationContext<'a> {
+    /// The server's root dir (`SUWAYOMI_ROOT_DIR` or the platform default).
+    pub root_dir: &'a Path,
+}
+
+/// One-time data handling for an upgrade that crosses `version`: it runs when the server
+/// last started below `version` and now starts at or above it. `run` has to be idempotent,
+/// because a failed run is retried on the next start.
+pub struct Migration {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub run: fn(&MigrationContext) -> Result<(), String>,
+}
+
+/// Registered upgrades, oldest first. Empty until a server release needs one.
+const MIGRATIONS: &[Migration] = &[];
+
+/// Runs the migrations a server upgrade calls for before the server at `jar` is spawned,
+/// and records its version in the state file at `state_file` once they all succeed.
+pub fn before_spawn(jar: &Path, state_file: &Path, root_dir: &Path) {
+    let Some(current) = jar_version(jar) else {
+        return;
+    };
+    let previous: Option<String> = launcher::load_state(state_file, VERSION_STATE_KEY);
+    let context = MigrationContext { root_dir };
+
+    match migrate(previous.as_deref(), &current, MIGRATIONS, &context) {
+        Ok(()) => {
+            if previous.as_deref() != Some(current.as_str()) {
+                if let Err(err) =
+                    launcher::store_state(state_file, VERSION_STATE_KEY, Some(&current))
+                {
+                    eprintln!("{err}");
+                }
+            }
+        }
+        // The old version stays recorded so the migration is tried again next time.
+        Err(err) => eprintln!("{err}"),
+    }
+}
+
+fn migrate(
+    previous: Option<&str>,
+    current: &str,
+    migrations: &[Migration],
+    context: &MigrationContext,
+) -> Result<(), String> {
+    // Without a record there is no telling which data layout is in place.
+    let Some(previous) = previous.filter(|previous| !previous.is_empty()) else {
+        return Ok(());
+    };
+    if compare_versions(current, previous) != Ordering::Greater {
+        return Ok(());
+    }
+
+    for migration in migrations.iter().filter(|migration| {
+        compare_versions(previous, migration.version) == Ordering::Less
+            && compare_versions(current, migration.version) != Ordering::Less
+    }) {
+        eprintln!(
+            "server upgraded from {previous} to {current}, running migration {} in {}",
+            migration.name,
+            context.root_dir.display()
+        );
+        (migration.run)(context)
+            .map_err(|err| format!("migration {} failed: {err}", migration.name))?;
+    }
+    Ok(())
+}
+
+/// Compares `v2.0.1727`- or `r1867`-style versions by their numeric parts; the leading
+/// letters are skipped and anything after the first non-numeric part (`-preview`) is ignored.
+pub(crate) fn compare_versions(left: &str, right: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim()
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .split('.')
+            .map_while(|part| {
+                let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .collect()
+    };
+
+    let (left, right) = (parts(left), parts(right));
+    let len = left.len().max(right.len());
+    let padded = |parts: &[u64]| -> Vec<u64> {
+        (0..len)
+            .map(|i| parts.get(i).copied().unwrap_or(0))
+            .collect()
+    };
+    padded(&left).cmp(&padded(&right))
+}
+
+/// The server version baked into the jar's manifest (`Specification-Version`).
+pub(crate) fn jar_version(jar: &Path) -> Option<String> {
+    manifest_attribute(jar, "Specification-Version")
+}
+
+/// The value of `name` in the jar's manifest, e.g. `Implementation-Version`.
+pub(crate) fn manifest_attribute(jar: &Path, name: &str) -> Option<String> {
+    let manifest = read_manifest(&mut File::open(jar).ok()?).ok()??;
+    manifest.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == name).then(|| value.trim().to_string())
+    })
+}
+
+/// Reads the manifest through the jar's central directory, which has the entry sizes even
+/// when the local headers leave them to a data descriptor after the data (bit 3). `Ok(None)`
+/// when there is no manifest or it is compressed in a way we do not read.
+fn read_manifest(jar: &mut (impl Read + Seek)) -> io::Result<Option<String>> {
+    let Some((directory_offset, directory_len)) = find_central_directory(jar)? else {
+        return Ok(None);
+    };
+    if directory_len > MAX_CENTRAL_DIRECTORY_BYTES {
+        return Ok(None);
+    }
+    let mut directory = vec![0; directory_len as usize];
+    jar.seek(SeekFrom::Start(directory_offset))?;
+    jar.read_exact(&mut directory)?;
+    let Some(entry) = find_entry(&directory, MANIFEST_ENTRY) else {
+        return Ok(None);
+    };
+
+    let mut header = [0; LOCAL_HEADER_LEN];
+    jar.seek(SeekFrom::Start(entry.local_header_offset))?;
+    jar.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+        return Ok(None);
+    }
+    // The local name and extra field may differ in length from the directory's copy.
+    let skip = i64::from(u16_at(&header, 26)) + i64::from(u16_at(&header, 28));
+    jar.seek(SeekFrom::Current(skip))?;
+
+    let data = jar.take(entry.compressed_size);
+    let mut manifest = String::new();
+    match entry.method {
+        0 => data
+            .take(MAX_MANIFEST_BYTES)
+            .read_to_string(&mut manifest)?,
+        8 => DeflateDecoder::new(data)
+            .take(MAX_MANIFEST_BYTES)
+            .read_to_string(&mut manifest)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(manifest))
+}
+
+struct ZipEntry {
+    method: u16,
+    compressed_size: u64,
+    local_header_offset: u64,
+}
+
+/// Offset and length of the central directory, from the end record at the end of the jar.
+fn find_central_directory(jar: &mut (impl Read + Seek)) -> io::Result<Option<(u64, u64)>> {
+    let len = jar.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((END_RECORD_LEN + MAX_ZIP_COMMENT_LEN) as u64);
+    let mut tail = vec![0; tail_len as usize];
+    jar.seek(SeekFrom::Start(len - tail_len))?;
+    jar.read_exact(&mut tail)?;
+
+    let Some(last_start) = tail.len().checked_sub(END_RECORD_LEN) else {
+        return Ok(None);
+    };
+    let record = (0..=last_start)
+        .rev()
+        .map(|at| &tail[at..])
+        .find(|record| u32_at(record, 0) == END_RECORD_SIGNATURE);
+    Ok(record.map(|record| (u64::from(u32_at(record, 16)), u64::from(u32_at(record, 12)))))
+}
+
+fn find_entry(directory: &[u8], name: &str) -> Option<ZipEntry> {
+    let mut at = 0;
+    while let Some(header) = directory.get(at..at + CENTRAL_HEADER_LEN) {
+        if u32_at(header, 0) != CENTRAL_HEADER_SIGNATURE {
+            return None;
+        }
+        let name_start = at + CENTRAL_HEADER_LEN;
+        let name_len = usize::from(u16_at(header, 28));
+        if directory.get(name_start..name_start + name_len)? == name.as_bytes() {
+            return Some(ZipEntry {
+                method: u16_at(header, 10),
+                compressed_size: u64::from(u32_at(header, 20)),
+                local_header_offset: u64::from(u32_at(header, 42)),
+            });
+        }
+        at = name_start
+            + name_len
+            + usize::from(u16_at(header, 30))
+            + usize::from(u16_at(header, 32));
+    }
+    None
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{jar_with_manifest, streamed_jar};
+    use super::*;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_run(context: &MigrationContext) -> Result<(), String> {
+        RUNS.fetch_add(1, AtomicOrdering::SeqCst);
+        fs::write(context.root_dir.join("migrated"), "").map_err(|err| err.to_string())
+    }
+
+    const COUNTED: &[Migration] = &[Migration {
+        name: "move-downloads",
+        version: "v2.1.0",
+        run: count_run,
+    }];
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(compare_versions("v2.0.1727", "v2.0.999"), Ordering::Greater);
+        assert_eq!(compare_versions("v2.1", "v2.1.0"), Ordering::Equal);
+        assert_eq!(
+            compare_versions("v2.1.0-preview", "v2.1.0"),
+            Ordering::Equal
+        );
+        assert_eq!(compare_versions("v1.9.9", "v2.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("r1867", "r1727"), Ordering::Greater);
+    }
+
+    #[test]
+    fn migrations_run_once_when_an_upgrade_crosses_their_version() {
+        let root = tempfile::tempdir().expect("temp dir");
+        let context = MigrationContext {
+            root_dir: root.path(),
+        };
+        let runs = || RUNS.load(AtomicOrdering::SeqCst);
+
+        migrate(None, "v2.1.3", COUNTED, &context).expect("first run");
+        migrate(Some("v2.1.0"), "v2.1.3", COUNTED, &context).expect("patch upgrade");
+        migrate(Some("v2.2.0"), "v2.1.3", COUNTED, &context).expect("downgrade");
+        assert_eq!(runs(), 0);
+
+        migrate(Some("v2.0.1727"), "v2.1.3", COUNTED, &context).expect("crossing upgrade");
+        assert_eq!(runs(), 1);
+        assert!(root.path().join("migrated").exists());
+    }
+
+    #[test]
+    fn version_is_read_from_the_jar_manifest_and_recorded() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let jar = dir.path().join("Suwayomi-Server.jar");
+        fs::write(
+            &jar,
+            jar_with_manifest("Manifest-Version: 1.0\r\nSpecification-Version: v2.1.3\r\n\r\n"),
+        )
+        .expect("write jar");
+        assert_eq!(jar_version(&jar).as_deref(), Some("v2.1.3"));
+
+        let state = dir.path().join("launcher").join("launcher-state.json");
+        before_spawn(&jar, &state, dir.path());
+        assert_eq!(
+            launcher::load_state::<String>(&state, VERSION_STATE_KEY).as_deref(),
+            Some("v2.1.3")
+        );
+    }
+
+    #[test]
+    fn manifests_with_sizes_after_the_data_are_read_too() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let jar = dir.path().join("Suwayomi-Server.jar");
+        let manifest = b"Manifest-Version: 1.0\r\nImplementation-Version: v2.1.1867\r\n\r\n";
+        let entries: &[(&str, &[u8])] = &[
+            ("META-INF/", b""),
+            ("suwayomi/tachidesk/Main.class", &[0xca, 0xfe, 0xba, 0xbe]),
+            (MANIFEST_ENTRY, manifest),
+        ];
+        fs::write(&jar, streamed_jar(entries)).expect("write jar");
+
+        assert_eq!(
+            manifest_attribute(&jar, "Implementation-Version").as_deref(),
+            Some("v2.1.1867")
+        );
+        fs::write(&jar, b"not a jar").expect("write jar");
+        assert_eq!(jar_version(&jar), None);
+    }
+}
+
+/// Minimal jars for tests that read a server version from the manifest.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{
+        CENTRAL_HEADER_SIGNATURE, END_RECORD_SIGNATURE, LOCAL_HEADER_SIGNATURE, MANIFEST_ENTRY,
+    };
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+
+    /// A jar whose only entry is a stored `META-INF/MANIFEST.MF`.
+    pub(crate) fn jar_with_manifest(manifest: &str) -> Vec<u8> {
+        jar(&[(MANIFEST_ENTRY, manifest.as_bytes())], false)
+    }
+
+    /// A jar with `entries` deflated and their sizes written after the data, the way `jar`
+    /// and most zip tools stream them.
+    pub(crate) fn streamed_jar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        jar(entries, true)
+    }
+
+    fn jar(entries: &[(&str, &[u8])], streamed: bool) -> Vec<u8> {
+        let mut jar = Vec::new();
+        let mut directory = Vec::new();
+
+        for (name, contents) in entries {
+            let (flags, method, data) = if streamed {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(contents).expect("deflate");
+                (0x08_u16, 8_u16, encoder.finish().expect("deflate"))
+            } else {
+                (0, 0, contents.to_vec())
+            };
+            let sizes = [data.len() as u32, contents.len() as u32];
+            let local_sizes = if streamed { [0, 0] } else { sizes };
+            let offset = jar.len() as u32;
+
+            jar.extend_from_slice(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            jar.extend_from_slice(&20_u16.to_le_bytes());
+            jar.extend_from_slice(&flags.to_le_bytes());
+            jar.extend_from_slice(&method.to_le_bytes());
+            // Modification time and date, CRC-32: not checked.
+            jar.extend_from_slice(&[0; 8]);
+            jar.extend_from_slice(&local_sizes[0].to_le_bytes());
+            jar.extend_from_slice(&local_sizes[1].to_le_bytes());
+            jar.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            jar.extend_from_slice(&0_u16.to_le_bytes());
+            jar.extend_from_slice(name.as_bytes());
+            jar.extend_from_slice(&data);
+            if streamed {
+                jar.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+                jar.extend_from_slice(&[0; 4]);
+                jar.extend_from_slice(&sizes[0].to_le_bytes());
+                jar.extend_from_slice(&sizes[1].to_le_bytes());
+            }
+
+            directory.extend_from_slice(&CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            directory.extend_from_slice(&[20, 0, 20, 0]);
+            directory.extend_from_slice(&flags.to_le_bytes());
+            directory.extend_from_slice(&method.to_le_bytes());
+            directory.extend_from_slice(&[0; 8]);
+            directory.extend_from_slice(&sizes[0].to_le_bytes());
+            directory.extend_from_slice(&sizes[1].to_le_bytes());
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            // Extra field and comment lengths, disk number, attributes.
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let directory_offset = jar.len() as u32;
+        let count = entries.len() as u16;
+        jar.extend_from_slice(&directory);
+        jar.extend_from_slice(&END_RECORD_SIGNATURE.to_le_bytes());
+        jar.extend_from_slice(&[0; 4]);
+        jar.extend_from_slice(&count.to_le_bytes());
+        jar.extend_from_slice(&count.to_le_bytes());
+        jar.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        jar.extend_from_slice(&directory_offset.to_le_bytes());
+        jar.extend_from_slice(&0_u16.to_le_bytes());
+        jar
+    }
+}
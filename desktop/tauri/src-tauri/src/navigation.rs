@@ -0,0 +1,212 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use url::Url;
+
+/// Host the webview serves bundled pages from on Windows (`http://tauri.localhost/...`).
+const BUNDLED_PAGES_HOST: &str = "tauri.localhost";
+
+/// Whether the webview may navigate to `url` itself. Web links must stay on the server's
+/// origin or one of the `extra_origins`; besides those only the bundled pages (`tauri:`
+/// and `about:blank`) load. Anything else, `file:` and `javascript:` included, is refused,
+/// since the server's pages could otherwise reach them.
+pub fn is_allowed(url: &Url, base_url: &str, extra_origins: &[String]) -> bool {
+    match url.scheme() {
+        "http" | "https" => {}
+        "tauri" => return true,
+        "about" => return url.as_str() == "about:blank",
+        _ => return false,
+    }
+    if url.host_str() == Some(BUNDLED_PAGES_HOST) {
+        return true;
+    }
+
+    let origin = url.origin();
+    std::iter::once(base_url)
+        .chain(extra_origins.iter().map(String::as_str))
+        .filter_map(|allowed| Url::parse(allowed).ok())
+        .any(|allowed| allowed.origin() == origin)
+}
+
+/// Origins besides the server's that stay in the webview, e.g. a single sign-on page in
+/// front of a remote server, from the comma-separated `SUWAYOMI_ALLOWED_ORIGINS`.
+pub fn extra_origins() -> Vec<String> {
+    let Ok(raw) = env::var("SUWAYOMI_ALLOWED_ORIGINS") else {
+        return Vec::new();
+    };
+
+    raw.split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .filter(|origin| {
+            let valid = Url::parse(origin).is_ok();
+            if !valid {
+                eprintln!("ignoring invalid origin {origin:?} in SUWAYOMI_ALLOWED_ORIGINS");
+            }
+            valid
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether a link the webview refused is worth handing to another app: web pages go to the
+/// browser, `mailto:` and `tel:` to their apps. Other schemes are dropped.
+pub fn opens_externally(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https" | "mailto" | "tel")
+}
+
+/// Opens `url` in the user's default browser (or mail client, for `mailto:`).
+pub fn open_externally(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        Command::new("explorer")
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    quiet(command.arg(url)).spawn().map(drop)
+}
+
+/// Shows `path` selected in the system file manager. Linux has no standard way to do that
+/// beyond file managers implementing `org.freedesktop.FileManager1`; without one, this opens
+/// the containing folder instead.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    fs::metadata(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("cannot reveal {}: {err}", path.display()),
+        )
+    })?;
+
+    select_in_file_manager(path)
+}
+
+#[cfg(windows)]
+fn select_in_file_manager(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer parses its own command line and wants only the path quoted, which the
+    // standard argument quoting cannot produce.
+    quiet(Command::new("explorer").raw_arg(format!("/select,\"{}\"", path.display())))
+        .spawn()
+        .map(drop)
+}
+
+#[cfg(target_os = "macos")]
+fn select_in_file_manager(path: &Path) -> io::Result<()> {
+    quiet(Command::new("open").arg("-R").arg(path))
+        .spawn()
+        .map(drop)
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn select_in_file_manager(path: &Path) -> io::Result<()> {
+    if show_items_over_dbus(path) {
+        return Ok(());
+    }
+    let folder = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    open_externally(&folder.to_string_lossy())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn show_items_over_dbus(path: &Path) -> bool {
+    let Ok(uri) = path
+        .canonicalize()
+        .map_err(drop)
+        .and_then(Url::from_file_path)
+    else {
+        return false;
+    };
+
+    quiet(Command::new("dbus-send").args([
+        "--session",
+        "--print-reply",
+        "--dest=org.freedesktop.FileManager1",
+        "--type=method_call",
+        "/org/freedesktop/FileManager1",
+        "org.freedesktop.FileManager1.ShowItems",
+    ]))
+    .arg(format!("array:string:{uri}"))
+    .arg("string:")
+    .status()
+    .is_ok_and(|status| status.success())
+}
+
+fn quiet(command: &mut Command) -> &mut Command {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_URL: &str = "http://127.0.0.1:4567";
+
+    fn allowed(url: &str, extra_origins: &[String]) -> bool {
+        is_allowed(
+            &Url::parse(url).expect("valid url"),
+            BASE_URL,
+            extra_origins,
+        )
+    }
+
+    #[test]
+    fn only_the_server_origin_stays_in_the_webview() {
+        assert!(allowed("http://127.0.0.1:4567/library", &[]));
+        assert!(!allowed("http://127.0.0.1:8080/", &[]));
+        assert!(!allowed("https://github.com/Suwayomi", &[]));
+        assert!(!allowed("mailto:someone@example.com", &[]));
+    }
+
+    #[test]
+    fn bundled_pages_and_extra_origins_are_allowed() {
+        let extra = vec!["https://sso.example.com".to_string()];
+
+        assert!(allowed("https://sso.example.com/login?next=/", &extra));
+        assert!(allowed("tauri://localhost/control.html", &[]));
+        assert!(allowed("http://tauri.localhost/control.html", &[]));
+        assert!(allowed("about:blank", &[]));
+    }
+
+    #[test]
+    fn other_schemes_are_refused_and_not_opened_elsewhere() {
+        for url in [
+            "file:///etc/passwd",
+            "javascript:alert(1)",
+            "blob:http://127.0.0.1:4567/0b4e",
+            "data:text/html,<script>alert(1)</script>",
+            "about:config",
+            "steam://run/1",
+        ] {
+            let parsed = Url::parse(url).expect("valid url");
+            assert!(!is_allowed(&parsed, BASE_URL, &[]), "{url}");
+            assert!(!opens_externally(&parsed), "{url}");
+        }
+        assert!(opens_externally(
+            &Url::parse("mailto:someone@example.com").expect("url")
+        ));
+        assert!(opens_externally(
+            &Url::parse("https://github.com/").expect("url")
+        ));
+    }
+
+    #[test]
+    fn revealing_a_missing_file_fails_before_launching_anything() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let missing = dir.path().join("server.log");
+
+        let err = reveal(&missing).expect_err("missing file");
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("server.log"), "{err}");
+    }
+}
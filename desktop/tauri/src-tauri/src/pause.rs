@@ -0,0 +1,122 @@
+use crate::http::{self, GraphqlError};
+use crate::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+const PAUSE_TIMEOUT: Duration = Duration::from_secs(10);
+/// The server has no maintenance mode; pausing stops the two things it does on its own,
+/// the downloader and a running library update.
+const PAUSE_MUTATION: &str = "mutation { stopDownloader(input: {}) { downloadStatus { state } } \
+     updateStop(input: {}) { clientMutationId } }";
+/// A stopped library update cannot be picked up again, so resuming only restarts the
+/// downloader with the queue it kept.
+const RESUME_MUTATION: &str =
+    "mutation { startDownloader(input: {}) { downloadStatus { state } } }";
+const UNSUPPORTED: &str = "this server has no API for pausing its downloads and updates";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseState {
+    pub paused: bool,
+    /// The downloader state the server reported back (`STARTED` or `STOPPED`).
+    pub downloader_state: String,
+}
+
+/// Pauses or resumes the connected server's background work without stopping it.
+pub fn set(paused: bool) -> Result<PauseState, String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    set_at(base_url.trim_end_matches('/'), paused)
+}
+
+fn set_at(base_url: &str, paused: bool) -> Result<PauseState, String> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let mutation = if paused {
+        PAUSE_MUTATION
+    } else {
+        RESUME_MUTATION
+    };
+    let action = if paused { "pausing" } else { "resuming" };
+    let data = match http::graphql(&url, mutation, None, PAUSE_TIMEOUT) {
+        Ok(data) => data,
+        Err(GraphqlError::Unreachable) => {
+            return Err(format!("server at {base_url} did not answer"))
+        }
+        // Servers from before the GraphQL API.
+        Err(GraphqlError::NotFound) => return Err(UNSUPPORTED.to_string()),
+        Err(GraphqlError::Query(message))
+            if message.contains("Downloader") || message.contains("updateStop") =>
+        {
+            return Err(UNSUPPORTED.to_string())
+        }
+        Err(err) => return Err(format!("{action} the server failed: {err}")),
+    };
+
+    let field = if paused {
+        "stopDownloader"
+    } else {
+        "startDownloader"
+    };
+    let Some(state) = data
+        .pointer(&format!("/{field}/downloadStatus/state"))
+        .and_then(Value::as_str)
+    else {
+        return Err(format!(
+            "{action} the server failed: no downloader state in the answer"
+        ));
+    };
+    eprintln!(
+        "server at {base_url} {}, downloader {state}",
+        if paused { "paused" } else { "resumed" }
+    );
+    Ok(PauseState {
+        paused,
+        downloader_state: state.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    #[test]
+    fn pausing_stops_the_downloader_and_resuming_starts_it() {
+        let base_url = serve(2, |request| {
+            if request.contains("stopDownloader") {
+                assert!(request.contains("updateStop"), "{request}");
+                respond(
+                    200,
+                    r#"{"data":{"stopDownloader":{"downloadStatus":{"state":"STOPPED"}},"updateStop":{"clientMutationId":null}}}"#,
+                )
+            } else {
+                respond(
+                    200,
+                    r#"{"data":{"startDownloader":{"downloadStatus":{"state":"STARTED"}}}}"#,
+                )
+            }
+        });
+
+        let paused = set_at(&base_url, true).expect("pause");
+        assert!(paused.paused);
+        assert_eq!(paused.downloader_state, "STOPPED");
+        assert_eq!(
+            set_at(&base_url, false).expect("resume").downloader_state,
+            "STARTED"
+        );
+    }
+
+    #[test]
+    fn servers_without_the_mutations_are_unsupported() {
+        let old = serve(1, |_| {
+            respond(
+                200,
+                r#"{"errors":[{"message":"Field 'stopDownloader' in type 'Mutation' is undefined"}]}"#,
+            )
+        });
+        assert_eq!(set_at(&old, true), Err(UNSUPPORTED.to_string()));
+
+        let legacy = serve(1, |_| respond(404, ""));
+        assert_eq!(set_at(&legacy, false), Err(UNSUPPORTED.to_string()));
+    }
+}
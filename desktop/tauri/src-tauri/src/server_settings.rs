@@ -0,0 +1,149 @@
+use crate::http::{self, GraphqlError};
+use crate::launcher;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub(crate) const SETTINGS_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Applied {
+    /// Changed through the settings API; no restart needed.
+    Live,
+    /// Written to `server.conf` and the managed server restarted.
+    Restarted,
+    /// Written to `server.conf`; takes effect when the server is next restarted.
+    OnNextStart,
+}
+
+/// A server setting, by its name in the settings API and its key in `server.conf`.
+pub(crate) struct Setting {
+    pub field: &'static str,
+    pub conf_key: &'static str,
+    /// What the setting is, for messages: "changing the {description} failed".
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Change {
+    pub applied: Applied,
+    /// Set after a restart, since the server may come back at a different URL.
+    pub base_url: Option<String>,
+}
+
+/// Sets `setting` live when the server has a settings API and through `server.conf`
+/// (restarting a managed server) otherwise.
+pub(crate) fn change(
+    setting: &Setting,
+    value: Value,
+    resource_dir: Option<PathBuf>,
+) -> Result<Change, String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+
+    if set_live(base_url.trim_end_matches('/'), setting, &value)? {
+        return Ok(Change {
+            applied: Applied::Live,
+            base_url: None,
+        });
+    }
+
+    let path = launcher::set_server_conf_value(setting.conf_key, &value.to_string())?;
+    eprintln!(
+        "server cannot change the {} live, updated {}",
+        setting.description,
+        path.display()
+    );
+
+    if !launcher::manages_server() {
+        return Ok(Change {
+            applied: Applied::OnNextStart,
+            base_url: None,
+        });
+    }
+
+    let bootstrap = launcher::restart_server(resource_dir).map_err(|err| err.to_string())?;
+    Ok(Change {
+        applied: Applied::Restarted,
+        base_url: Some(bootstrap.base_url),
+    })
+}
+
+/// `Ok(false)` when the server has no live settings endpoint for `setting`.
+pub(crate) fn set_live(base_url: &str, setting: &Setting, value: &Value) -> Result<bool, String> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let field = setting.field;
+    let mutation = format!(
+        "mutation {{ setSettings(input: {{ settings: {{ {field}: {value} }} }}) \
+         {{ settings {{ {field} }} }} }}"
+    );
+    let message = match http::graphql(&url, &mutation, None, SETTINGS_TIMEOUT) {
+        Ok(data) => {
+            let applied = data.pointer(&format!("/setSettings/settings/{field}"));
+            return Ok(applied == Some(value));
+        }
+        Err(GraphqlError::Query(message)) => message,
+        Err(GraphqlError::Unreachable) => {
+            return Err(format!("server at {base_url} did not answer"))
+        }
+        Err(_) => return Ok(false),
+    };
+
+    // Schema errors ("unknown field setSettings" etc.) mean an older server; anything else
+    // is a real failure worth showing.
+    if message.is_empty() || message.contains("setSettings") || message.contains(field) {
+        return Ok(false);
+    }
+    Err(format!(
+        "changing the {} failed: {message}",
+        setting.description
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    const DEBUG_LOGS: Setting = Setting {
+        field: "debugLogsEnabled",
+        conf_key: "server.debugLogsEnabled",
+        description: "log level",
+    };
+
+    #[test]
+    fn live_change_needs_the_settings_mutation() {
+        let live = serve(1, |request| {
+            assert!(request.starts_with("POST /api/graphql"));
+            assert!(request.contains("debugLogsEnabled: true"), "{request}");
+            respond(
+                200,
+                r#"{"data":{"setSettings":{"settings":{"debugLogsEnabled":true}}}}"#,
+            )
+        });
+        assert_eq!(set_live(&live, &DEBUG_LOGS, &Value::from(true)), Ok(true));
+
+        let old = serve(1, |_| {
+            respond(
+                200,
+                r#"{"errors":[{"message":"Field 'setSettings' is undefined"}]}"#,
+            )
+        });
+        assert_eq!(set_live(&old, &DEBUG_LOGS, &Value::from(true)), Ok(false));
+
+        let legacy = serve(1, |_| respond(404, ""));
+        assert_eq!(
+            set_live(&legacy, &DEBUG_LOGS, &Value::from(false)),
+            Ok(false)
+        );
+
+        let failing = serve(1, |_| {
+            respond(200, r#"{"errors":[{"message":"settings are read-only"}]}"#)
+        });
+        assert_eq!(
+            set_live(&failing, &DEBUG_LOGS, &Value::from(true)),
+            Err("changing the log level failed: settings are read-only".to_string())
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use crate::http::{self, GraphqlError};
+use crate::{cli, events, launcher};
+use serde::Serialize;
+use serde_json::Value;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The server fetches every configured extension repo before answering.
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(60);
+const FETCH_EXTENSIONS_MUTATION: &str =
+    "mutation { fetchExtensions(input: {}) { extensions { pkgName } } }";
+/// Servers from before the GraphQL API refresh the repos when the list is requested.
+const LEGACY_EXTENSION_LIST: &str = "/api/v1/extension/list";
+const SETTINGS_TIMEOUT: Duration = Duration::from_secs(10);
+const REPO_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const EXTENSION_REPOS_QUERY: &str = "query { settings { extensionRepos } }";
+/// Loading every installed extension can take minutes on a slow disk with many of them.
+pub const SOURCES_READY_TIMEOUT: Duration = Duration::from_secs(180);
+/// A restarted server is only waited on briefly: the app is already open by then and shows
+/// the sources as they load.
+pub const SOURCES_RESTART_TIMEOUT: Duration = Duration::from_secs(10);
+const SOURCES_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const INSTALLED_EXTENSIONS_QUERY: &str = "query { extensions(condition: {isInstalled: true}) \
+     { nodes { pkgName source { totalCount } } } }";
+
+/// Whether the launcher's machine can fetch one of the server's extension repos.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatus {
+    pub url: String,
+    pub reachable: bool,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Makes the connected server re-fetch its extension repos.
+pub fn refresh() -> Result<(), String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    let count = refresh_at(base_url.trim_end_matches('/'))?;
+    eprintln!("server repos list {count} extension(s) after refreshing");
+    Ok(())
+}
+
+fn refresh_at(base_url: &str) -> Result<usize, String> {
+    match refresh_graphql(base_url) {
+        Ok(count) => Ok(count),
+        Err(Fallback::Legacy) => refresh_legacy(base_url),
+        Err(Fallback::Failed(reason)) => Err(reason),
+    }
+}
+
+enum Fallback {
+    /// The server predates the `fetchExtensions` mutation.
+    Legacy,
+    Failed(String),
+}
+
+fn refresh_graphql(base_url: &str) -> Result<usize, Fallback> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let data = match http::graphql(&url, FETCH_EXTENSIONS_MUTATION, None, REFRESH_TIMEOUT) {
+        Ok(data) => data,
+        Err(GraphqlError::NotFound) => return Err(Fallback::Legacy),
+        // "Field 'fetchExtensions' is undefined" and the like: an early GraphQL server.
+        Err(GraphqlError::Query(message)) if message.contains("fetchExtensions") => {
+            return Err(Fallback::Legacy)
+        }
+        Err(GraphqlError::Unreachable) => {
+            return Err(Fallback::Failed(format!(
+                "server at {base_url} did not answer"
+            )))
+        }
+        Err(err) => {
+            return Err(Fallback::Failed(format!(
+                "refreshing sources failed: {err}"
+            )))
+        }
+    };
+
+    data.pointer("/fetchExtensions/extensions")
+        .and_then(Value::as_array)
+        .map(Vec::len)
+        .ok_or_else(|| {
+            Fallback::Failed("refreshing sources failed: no extensions in the answer".to_string())
+        })
+}
+
+fn refresh_legacy(base_url: &str) -> Result<usize, String> {
+    let response = http::get(
+        &format!("{base_url}{LEGACY_EXTENSION_LIST}"),
+        REFRESH_TIMEOUT,
+    )
+    .ok_or_else(|| format!("server at {base_url} did not answer"))?;
+    if response.status != 200 {
+        return Err(format!(
+            "refreshing sources failed (HTTP {})",
+            response.status
+        ));
+    }
+
+    serde_json::from_slice::<Vec<Value>>(&response.body)
+        .map(|extensions| extensions.len())
+        .map_err(|_| "unexpected response from server".to_string())
+}
+
+/// `SUWAYOMI_WAIT_FOR_SOURCES=1`: a started server only counts as ready once its
+/// extensions have loaded their sources, so the UI does not open on an empty source list.
+pub fn wait_for_sources_enabled() -> bool {
+    cli::env_flag("SUWAYOMI_WAIT_FOR_SOURCES")
+}
+
+/// Polls until every installed extension of the server at `base_url` has sources, or until
+/// `timeout`. Returns `false` on timeout. Servers that cannot report on their extensions
+/// count as ready right away.
+pub fn wait_until_loaded(base_url: &str, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut waiting_on = None;
+
+    loop {
+        match loading_extensions(base_url) {
+            Some(0) => return true,
+            Some(loading) if waiting_on != Some(loading) => {
+                eprintln!("waiting for {loading} extension(s) to load their sources");
+                waiting_on = Some(loading);
+            }
+            // Busy starting up, most likely; asked again below.
+            Some(_) | None => {}
+        }
+
+        if Instant::now() >= deadline {
+            eprintln!("continuing while extensions are still loading at {base_url}");
+            return false;
+        }
+        thread::sleep(SOURCES_POLL_INTERVAL);
+    }
+}
+
+/// How many installed extensions have no sources loaded yet. `Some(0)` too when the server
+/// predates the query; `None` when it gave no usable answer, errors included.
+fn loading_extensions(base_url: &str) -> Option<usize> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let data = match http::graphql(&url, INSTALLED_EXTENSIONS_QUERY, None, SETTINGS_TIMEOUT) {
+        Ok(data) => data,
+        Err(GraphqlError::NotFound) => return Some(0),
+        // "Field 'extensions' in type 'Query' is undefined" and the like: an older schema.
+        Err(GraphqlError::Query(message))
+            if message.contains("undefined") || message.contains("Validation error") =>
+        {
+            return Some(0)
+        }
+        Err(_) => return None,
+    };
+
+    let extensions = data
+        .pointer("/extensions/nodes")
+        .and_then(Value::as_array)?;
+    Some(
+        extensions
+            .iter()
+            .filter(|extension| {
+                extension
+                    .pointer("/source/totalCount")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0)
+                    == 0
+            })
+            .count(),
+    )
+}
+
+/// Probes every extension repo the connected server is configured with. The server has no
+/// API to do this itself, so repos are fetched from here, which shares its network when
+/// the server is local.
+pub fn check_repos() -> Result<Vec<RepoStatus>, String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    let repos = extension_repos(base_url.trim_end_matches('/'))?;
+    Ok(probe_repos(&repos, REPO_PROBE_TIMEOUT))
+}
+
+fn extension_repos(base_url: &str) -> Result<Vec<String>, String> {
+    let url = format!("{base_url}{}", launcher::GRAPHQL_ENDPOINT);
+    let data = http::graphql(&url, EXTENSION_REPOS_QUERY, None, SETTINGS_TIMEOUT).map_err(
+        |err| match err {
+            GraphqlError::Unreachable => format!("server at {base_url} did not answer"),
+            err => format!("server does not report its extension repos: {err}"),
+        },
+    )?;
+
+    let Some(repos) = data
+        .pointer("/settings/extensionRepos")
+        .and_then(Value::as_array)
+    else {
+        return Err("server does not report its extension repos".to_string());
+    };
+    Ok(repos
+        .iter()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect())
+}
+
+fn probe_repos(repos: &[String], timeout: Duration) -> Vec<RepoStatus> {
+    thread::scope(|scope| {
+        let probes: Vec<_> = repos
+            .iter()
+            .map(|repo| scope.spawn(move || probe_repo(repo, timeout)))
+            .collect();
+        probes
+            .into_iter()
+            .zip(repos)
+            .map(|(probe, repo)| {
+                probe
+                    .join()
+                    .unwrap_or_else(|_| unreachable_repo(repo, "probe failed".to_string()))
+            })
+            .collect()
+    })
+}
+
+/// Repos are third-party hosts, so the server credentials are not sent.
+fn probe_repo(url: &str, timeout: Duration) -> RepoStatus {
+    let started = Instant::now();
+    let Some(response) = http::get_anonymous(url, timeout) else {
+        return unreachable_repo(url, "no response".to_string());
+    };
+    if !(200..300).contains(&response.status) {
+        return unreachable_repo(url, format!("HTTP {}", response.status));
+    }
+
+    RepoStatus {
+        url: url.to_string(),
+        reachable: true,
+        latency_ms: Some(events::millis(started.elapsed())),
+        error: None,
+    }
+}
+
+fn unreachable_repo(url: &str, error: String) -> RepoStatus {
+    RepoStatus {
+        url: url.to_string(),
+        reachable: false,
+        latency_ms: None,
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::test_support::{respond, serve};
+
+    #[test]
+    fn refreshes_through_graphql() {
+        let base_url = serve(1, |request| {
+            assert!(request.starts_with("POST /api/graphql"));
+            respond(
+                200,
+                r#"{"data":{"fetchExtensions":{"extensions":[{"pkgName":"a"},{"pkgName":"b"}]}}}"#,
+            )
+        });
+
+        assert_eq!(refresh_at(&base_url), Ok(2));
+    }
+
+    #[test]
+    fn falls_back_to_the_rest_api_on_older_servers() {
+        let base_url = serve(2, |request| {
+            if request.starts_with("POST") {
+                respond(404, "")
+            } else {
+                assert!(request.starts_with("GET /api/v1/extension/list"));
+                respond(200, r#"[{"pkgName":"a"}]"#)
+            }
+        });
+
+        assert_eq!(refresh_at(&base_url), Ok(1));
+    }
+
+    #[test]
+    fn reports_graphql_errors() {
+        let base_url = serve(1, |_| {
+            respond(
+                200,
+                r#"{"errors":[{"message":"repo unreachable"}],"data":null}"#,
+            )
+        });
+
+        assert_eq!(
+            refresh_at(&base_url),
+            Err("refreshing sources failed: repo unreachable".to_string())
+        );
+    }
+
+    #[test]
+    fn waits_until_every_installed_extension_has_sources() {
+        let polls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = polls.clone();
+        let base_url = serve(3, move |request| {
+            assert!(request.contains("isInstalled: true"), "{request}");
+            let loaded = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) >= 2;
+            respond(
+                200,
+                &format!(
+                    r#"{{"data":{{"extensions":{{"nodes":[{{"pkgName":"a","source":{{"totalCount":1}}}},{{"pkgName":"b","source":{{"totalCount":{}}}}}]}}}}}}"#,
+                    u8::from(loaded)
+                ),
+            )
+        });
+
+        assert!(wait_until_loaded(&base_url, Duration::from_secs(10)));
+        assert_eq!(polls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn waiting_for_sources_gives_up_or_skips_old_servers() {
+        let stuck = serve(usize::MAX, |_| {
+            respond(
+                200,
+                r#"{"data":{"extensions":{"nodes":[{"pkgName":"a","source":{"totalCount":0}}]}}}"#,
+            )
+        });
+        assert!(!wait_until_loaded(&stuck, Duration::from_millis(200)));
+        let failing = serve(usize::MAX, |_| {
+            respond(200, r#"{"errors":[{"message":"database is locked"}]}"#)
+        });
+        assert!(!wait_until_loaded(&failing, Duration::from_millis(200)));
+
+        let old = serve(1, |_| {
+            respond(
+                200,
+                r#"{"errors":[{"message":"Field 'extensions' in type 'Query' is undefined"}]}"#,
+            )
+        });
+        assert!(wait_until_loaded(&old, Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn reads_repos_from_settings() {
+        let base_url = serve(1, |request| {
+            assert!(request.contains("extensionRepos"));
+            respond(
+                200,
+                r#"{"data":{"settings":{"extensionRepos":["https://a.example/index.min.json"]}}}"#,
+            )
+        });
+
+        assert_eq!(
+            extension_repos(&base_url),
+            Ok(vec!["https://a.example/index.min.json".to_string()])
+        );
+    }
+
+    #[test]
+    fn probes_report_reachability_per_repo() {
+        let ok = serve(1, |_| respond(200, "[]"));
+        let missing = serve(1, |_| respond(404, ""));
+        let repos = vec![
+            format!("{ok}/index.min.json"),
+            format!("{missing}/index.min.json"),
+        ];
+
+        let statuses = probe_repos(&repos, Duration::from_secs(2));
+
+        assert!(statuses[0].reachable && statuses[0].latency_ms.is_some());
+        assert!(!statuses[1].reachable);
+        assert_eq!(statuses[1].error.as_deref(), Some("HTTP 404"));
+        assert_eq!(statuses[1].url, repos[1]);
+    }
+}
@@ -0,0 +1,803 @@
+use crate::events::{self, EventKind};
+use crate::launcher::{self, LauncherBootstrap, LauncherError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_OFFLINE_GRACE: Duration = Duration::from_secs(3);
+const CONNECTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// `0`: a crashing server is restarted for as long as it keeps crashing.
+const DEFAULT_MAX_RESTART_ATTEMPTS: u32 = 0;
+const POLICY_STATE_KEY: &str = "reconnectPolicy";
+/// The longest wait between attempts to restart a server that keeps failing to start.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// The policy saved from the UI, read from the launcher state file on first use.
+static SAVED_POLICY: Lazy<Mutex<Option<ReconnectPolicy>>> =
+    Lazy::new(|| Mutex::new(load_policy(&launcher::state_file())));
+
+/// Bumped by [`wake`]; the loops sleep on the condvar until it changes or their interval
+/// is up.
+static WAKE: (Mutex<u64>, Condvar) = (Mutex::new(0), Condvar::new());
+
+/// How eagerly the supervisor gives up on an unresponsive server. A server is only
+/// considered dead after `failure_threshold` consecutive failed probes, i.e. roughly
+/// `interval * failure_threshold` of continuous unhealthiness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupervisorPolicy {
+    pub interval: Duration,
+    pub failure_threshold: u32,
+    /// Restarts in a row, without the server becoming healthy in between, before the
+    /// supervisor gives up on it; `0` never gives up.
+    pub max_restart_attempts: u32,
+}
+
+impl Default for SupervisorPolicy {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INTERVAL,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            max_restart_attempts: DEFAULT_MAX_RESTART_ATTEMPTS,
+        }
+    }
+}
+
+impl SupervisorPolicy {
+    /// The policy in effect right now; it changes when the UI saves a new one.
+    pub fn current() -> Self {
+        Self {
+            interval: interval_setting().value,
+            failure_threshold: failure_threshold_setting().value,
+            max_restart_attempts: max_restart_attempts_setting().value,
+        }
+    }
+}
+
+/// The reconnect timing the UI can tune, in whole seconds. Saved to the launcher dir; an
+/// environment variable for the same knob still takes precedence over the saved value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReconnectPolicy {
+    /// How long the server may be unreachable before the UI shows it as offline.
+    pub offline_grace_secs: u64,
+    /// How often the supervisor probes the managed server.
+    pub probe_interval_secs: u64,
+    /// Failed probes in a row before the managed server is restarted.
+    pub failure_threshold: u32,
+    /// See [`SupervisorPolicy::max_restart_attempts`].
+    pub max_restart_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            offline_grace_secs: DEFAULT_OFFLINE_GRACE.as_secs(),
+            probe_interval_secs: DEFAULT_INTERVAL.as_secs(),
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            max_restart_attempts: DEFAULT_MAX_RESTART_ATTEMPTS,
+        }
+    }
+}
+
+/// The reconnect policy in effect, saved or not.
+pub fn reconnect_policy() -> ReconnectPolicy {
+    ReconnectPolicy {
+        offline_grace_secs: offline_grace_setting().value.as_secs(),
+        probe_interval_secs: interval_setting().value.as_secs(),
+        failure_threshold: failure_threshold_setting().value,
+        max_restart_attempts: max_restart_attempts_setting().value,
+    }
+}
+
+/// Saves `policy` and puts it into effect without a restart. Returns the policy now in
+/// effect, which differs where an environment variable overrides a value.
+pub fn set_reconnect_policy(policy: ReconnectPolicy) -> Result<ReconnectPolicy, String> {
+    if policy.probe_interval_secs == 0 {
+        return Err("the probe interval must be at least one second".to_string());
+    }
+    if policy.failure_threshold == 0 {
+        return Err("the failure threshold must be at least 1".to_string());
+    }
+
+    save_policy(&launcher::state_file(), &policy)?;
+    *SAVED_POLICY
+        .lock()
+        .expect("reconnect policy mutex poisoned") = Some(policy);
+    // The loops pick the new timing up on their next tick.
+    wake();
+    Ok(reconnect_policy())
+}
+
+fn saved_policy() -> Option<ReconnectPolicy> {
+    *SAVED_POLICY
+        .lock()
+        .expect("reconnect policy mutex poisoned")
+}
+
+/// `None` when nothing was saved yet or the saved policy is unreadable, so defaults apply.
+fn load_policy(path: &Path) -> Option<ReconnectPolicy> {
+    launcher::load_state(path, POLICY_STATE_KEY)
+}
+
+fn save_policy(path: &Path, policy: &ReconnectPolicy) -> Result<(), String> {
+    launcher::store_state(path, POLICY_STATE_KEY, Some(policy))
+}
+
+fn interval_setting() -> Setting<Duration> {
+    Setting::from_env(
+        "SUWAYOMI_SUPERVISOR_INTERVAL_SECS",
+        DEFAULT_INTERVAL,
+        |secs| (secs > 0).then(|| Duration::from_secs(secs)),
+    )
+    .or_saved(saved_policy().map(|policy| Duration::from_secs(policy.probe_interval_secs.max(1))))
+}
+
+fn failure_threshold_setting() -> Setting<u32> {
+    Setting::from_env(
+        "SUWAYOMI_SUPERVISOR_FAILURE_THRESHOLD",
+        DEFAULT_FAILURE_THRESHOLD,
+        |n| (n > 0).then(|| n.min(u64::from(u32::MAX)) as u32),
+    )
+    .or_saved(saved_policy().map(|policy| policy.failure_threshold.max(1)))
+}
+
+fn max_restart_attempts_setting() -> Setting<u32> {
+    Setting::from_env(
+        "SUWAYOMI_SUPERVISOR_MAX_RESTARTS",
+        DEFAULT_MAX_RESTART_ATTEMPTS,
+        |n| Some(n.min(u64::from(u32::MAX)) as u32),
+    )
+    .or_saved(saved_policy().map(|policy| policy.max_restart_attempts))
+}
+
+fn offline_grace_setting() -> Setting<Duration> {
+    Setting::from_env(
+        "SUWAYOMI_OFFLINE_GRACE_SECS",
+        DEFAULT_OFFLINE_GRACE,
+        |secs| Some(Duration::from_secs(secs)),
+    )
+    .or_saved(saved_policy().map(|policy| Duration::from_secs(policy.offline_grace_secs)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingSource {
+    Default,
+    Env,
+    /// Saved from the UI with [`set_reconnect_policy`].
+    Saved,
+}
+
+/// An effective value and where it came from. An unparsable or out-of-range environment
+/// value counts as unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: SettingSource,
+}
+
+impl<T> Setting<T> {
+    pub(crate) fn from_env(name: &str, default: T, accept: impl FnOnce(u64) -> Option<T>) -> Self {
+        match env_number(name).and_then(accept) {
+            Some(value) => Self {
+                value,
+                source: SettingSource::Env,
+            },
+            None => Self {
+                value: default,
+                source: SettingSource::Default,
+            },
+        }
+    }
+
+    /// A saved value replaces the default but not an environment variable.
+    fn or_saved(self, saved: Option<T>) -> Self {
+        match saved {
+            Some(value) if self.source == SettingSource::Default => Self {
+                value,
+                source: SettingSource::Saved,
+            },
+            _ => self,
+        }
+    }
+}
+
+/// A duration in the form the UI gets it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingSetting {
+    pub millis: u64,
+    pub source: SettingSource,
+}
+
+impl From<Setting<Duration>> for TimingSetting {
+    fn from(setting: Setting<Duration>) -> Self {
+        Self {
+            millis: events::millis(setting.value),
+            source: setting.source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountSetting {
+    pub value: u32,
+    pub source: SettingSource,
+}
+
+impl From<Setting<u32>> for CountSetting {
+    fn from(setting: Setting<u32>) -> Self {
+        Self {
+            value: setting.value,
+            source: setting.source,
+        }
+    }
+}
+
+/// The timing the launcher actually runs with, for support requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeSettings {
+    /// How long a spawned server gets to answer its health check.
+    pub startup_timeout: TimingSetting,
+    pub startup_retries: CountSetting,
+    /// How long a stopping server gets before it is killed.
+    pub shutdown_timeout: TimingSetting,
+    /// How often the supervisor probes the managed server.
+    pub poll_interval: TimingSetting,
+    /// How long a single health probe waits for an answer.
+    pub health_timeout: TimingSetting,
+    pub failure_threshold: CountSetting,
+    pub max_restart_attempts: CountSetting,
+    pub offline_grace: TimingSetting,
+}
+
+pub fn runtime_settings() -> RuntimeSettings {
+    let fixed = |value| Setting {
+        value,
+        source: SettingSource::Default,
+    };
+
+    RuntimeSettings {
+        startup_timeout: fixed(launcher::STARTUP_TIMEOUT).into(),
+        startup_retries: launcher::startup_retries().into(),
+        shutdown_timeout: fixed(launcher::SHUTDOWN_TIMEOUT).into(),
+        poll_interval: interval_setting().into(),
+        health_timeout: fixed(launcher::HEALTH_PROBE_TIMEOUT).into(),
+        failure_threshold: failure_threshold_setting().into(),
+        max_restart_attempts: max_restart_attempts_setting().into(),
+        offline_grace: offline_grace_setting().into(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Healthy,
+    /// Failing, but not for long enough to act on yet.
+    Degraded {
+        consecutive_failures: u32,
+    },
+    Dead,
+}
+
+/// Turns a stream of probe results into a verdict, so a GC pause or a single 503 does not
+/// trigger a restart while an exited process is acted on immediately.
+#[derive(Debug)]
+pub struct HealthTracker {
+    failure_threshold: u32,
+    consecutive_failures: u32,
+}
+
+impl HealthTracker {
+    pub fn new(failure_threshold: u32) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            consecutive_failures: 0,
+        }
+    }
+
+    pub fn observe(&mut self, exited: bool, healthy: bool) -> Verdict {
+        if exited {
+            self.consecutive_failures = 0;
+            return Verdict::Dead;
+        }
+
+        if healthy {
+            self.consecutive_failures = 0;
+            return Verdict::Healthy;
+        }
+
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= self.failure_threshold {
+            self.consecutive_failures = 0;
+            Verdict::Dead
+        } else {
+            Verdict::Degraded {
+                consecutive_failures: self.consecutive_failures,
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    /// Applies a changed policy; failures counted so far still count.
+    pub fn set_failure_threshold(&mut self, failure_threshold: u32) {
+        self.failure_threshold = failure_threshold.max(1);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionChange {
+    Lost,
+    Restored,
+}
+
+/// Decides when the UI should show the server as offline: only after `grace` of continuous
+/// failure, so a momentary hiccup does not flash the offline state. Recovery is reported
+/// on the first healthy probe.
+#[derive(Debug)]
+pub struct ConnectionTracker {
+    grace: Duration,
+    failing_since: Option<Instant>,
+    offline: bool,
+}
+
+impl ConnectionTracker {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            failing_since: None,
+            offline: false,
+        }
+    }
+
+    pub fn observe(&mut self, healthy: bool, now: Instant) -> Option<ConnectionChange> {
+        if healthy {
+            self.failing_since = None;
+            return std::mem::take(&mut self.offline).then_some(ConnectionChange::Restored);
+        }
+
+        let failing_since = *self.failing_since.get_or_insert(now);
+        if self.offline || now.duration_since(failing_since) < self.grace {
+            return None;
+        }
+
+        self.offline = true;
+        Some(ConnectionChange::Lost)
+    }
+
+    pub fn set_grace(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+}
+
+/// How long the server may be unreachable before it is reported offline, from
+/// `SUWAYOMI_OFFLINE_GRACE_SECS` or the saved policy (`0` reports the first failed probe).
+pub fn offline_grace() -> Duration {
+    offline_grace_setting().value
+}
+
+/// Watches whichever server the app is using, spawned or remote, and calls `on_change`
+/// with its base URL when it goes offline or comes back.
+pub fn monitor_connection<F>(on_change: F)
+where
+    F: Fn(ConnectionChange, &str) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut tracker = ConnectionTracker::new(offline_grace());
+
+        loop {
+            sleep_unless_woken(CONNECTION_POLL_INTERVAL);
+
+            // Idle rather than stop: an exit can still be called off.
+            if launcher::is_shutting_down() {
+                continue;
+            }
+            tracker.set_grace(offline_grace());
+
+            let Some(base_url) = launcher::active_base_url() else {
+                continue;
+            };
+
+            let healthy = launcher::is_server_healthy(&base_url);
+            if let Some(change) = tracker.observe(healthy, Instant::now()) {
+                on_change(change, &base_url);
+            }
+        }
+    });
+}
+
+/// Starts the background supervisor, following [`SupervisorPolicy::current`]. `on_restart`
+/// receives the new base URL whenever the server had to be restarted.
+pub fn spawn<F>(resource_dir: Option<PathBuf>, on_restart: F)
+where
+    F: Fn(&str) + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut policy = SupervisorPolicy::current();
+        let mut tracker = HealthTracker::new(policy.failure_threshold);
+        let mut restarts = Restarts::default();
+
+        loop {
+            sleep_unless_woken(policy.interval);
+
+            // Idle rather than stop: an exit can still be called off.
+            if launcher::is_shutting_down() {
+                continue;
+            }
+            policy = SupervisorPolicy::current();
+            tracker.set_failure_threshold(policy.failure_threshold);
+
+            let base_url = match launcher::managed_server_state() {
+                Some(state) => {
+                    restarts.server_found();
+                    let healthy = !state.exited && launcher::is_server_healthy(&state.base_url);
+                    let verdict = tracker.observe(state.exited, healthy);
+                    if verdict == Verdict::Healthy {
+                        restarts.reset();
+                    }
+                    if verdict != Verdict::Dead || launcher::is_shutting_down() {
+                        continue;
+                    }
+
+                    if state.exited {
+                        events::record(
+                            EventKind::Crashed,
+                            format!("server at {} exited", state.base_url),
+                        );
+                    } else {
+                        events::record(
+                            EventKind::Unresponsive,
+                            format!("server at {} stopped responding", state.base_url),
+                        );
+                    }
+                    state.base_url
+                }
+                // A failed restart leaves no process to report; it is tried again once
+                // its backoff is up.
+                None if restarts.is_retrying() => match restarts.due(Instant::now()) {
+                    Some(base_url) => base_url.to_string(),
+                    None => continue,
+                },
+                None => {
+                    tracker.reset();
+                    restarts.reset();
+                    continue;
+                }
+            };
+
+            if restarts.exhausted(&policy) {
+                // Left stopped, like a server stopped from the control panel, until
+                // something starts one again.
+                let count = restarts.give_up();
+                eprintln!("server at {base_url} is down, giving up after {count} restarts");
+                events::record(
+                    EventKind::Failed,
+                    format!("gave up on the server after {count} restarts"),
+                );
+                launcher::stop_server();
+                continue;
+            }
+
+            eprintln!("server at {base_url} is down, restarting");
+            // The app may have switched to another server and kept this one for later.
+            let active = launcher::active_base_url().filter(|url| *url != base_url);
+            let restart = || launcher::restart_server(resource_dir.clone());
+            match restarts.attempt(&base_url, policy.interval, Instant::now(), restart) {
+                Ok(bootstrap) => {
+                    events::record(
+                        EventKind::Restarted,
+                        format!("server restarted at {}", bootstrap.base_url),
+                    );
+                    match active {
+                        Some(active) => {
+                            launcher::connected(active);
+                        }
+                        None => on_restart(&bootstrap.base_url),
+                    }
+                }
+                Err(err) => {
+                    eprintln!("failed to restart server: {err}");
+                    events::record(EventKind::Failed, format!("restart failed: {err}"));
+                }
+            }
+        }
+    });
+}
+
+/// Restarts since the managed server was last healthy. A restart that fails leaves no
+/// process behind, so the server it was for is kept here to be tried again, each attempt
+/// waiting twice as long as the one before.
+#[derive(Debug, Default)]
+struct Restarts {
+    count: u32,
+    failed: Option<FailedRestart>,
+}
+
+#[derive(Debug)]
+struct FailedRestart {
+    base_url: String,
+    retry_at: Instant,
+}
+
+impl Restarts {
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// A managed server is running again, whoever started it.
+    fn server_found(&mut self) {
+        self.failed = None;
+    }
+
+    fn is_retrying(&self) -> bool {
+        self.failed.is_some()
+    }
+
+    /// The server a failed restart was for, once it is time to try again.
+    fn due(&self, now: Instant) -> Option<&str> {
+        let failed = self.failed.as_ref()?;
+        (now >= failed.retry_at).then_some(failed.base_url.as_str())
+    }
+
+    fn exhausted(&self, policy: &SupervisorPolicy) -> bool {
+        policy.max_restart_attempts > 0 && self.count >= policy.max_restart_attempts
+    }
+
+    /// Stops retrying, returning how many restarts were tried.
+    fn give_up(&mut self) -> u32 {
+        self.failed = None;
+        self.count
+    }
+
+    fn attempt(
+        &mut self,
+        base_url: &str,
+        interval: Duration,
+        now: Instant,
+        restart: impl FnOnce() -> Result<LauncherBootstrap, LauncherError>,
+    ) -> Result<LauncherBootstrap, LauncherError> {
+        self.count += 1;
+        let result = restart();
+        self.failed = result.as_ref().err().map(|_| {
+            let backoff = interval.saturating_mul(1 << (self.count - 1).min(16));
+            FailedRestart {
+                base_url: base_url.to_string(),
+                retry_at: now + backoff.min(MAX_RESTART_BACKOFF),
+            }
+        });
+        result
+    }
+}
+
+/// Makes the supervisor and connection monitor probe right away instead of at their next
+/// tick, e.g. when the user comes back to the window after the machine slept.
+pub fn wake() {
+    let (generation, woken) = &WAKE;
+    *generation.lock().expect("wake mutex poisoned") += 1;
+    woken.notify_all();
+}
+
+fn sleep_unless_woken(timeout: Duration) {
+    let (generation, woken) = &WAKE;
+    let guard = generation.lock().expect("wake mutex poisoned");
+    let asleep_at = *guard;
+    let _ = woken
+        .wait_timeout_while(guard, timeout, |generation| *generation == asleep_at)
+        .expect("wake mutex poisoned");
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    env::var(name).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn tracker_tolerates_brief_blips() {
+        let mut tracker = HealthTracker::new(3);
+
+        assert_eq!(
+            tracker.observe(false, false),
+            Verdict::Degraded {
+                consecutive_failures: 1
+            }
+        );
+        assert_eq!(
+            tracker.observe(false, false),
+            Verdict::Degraded {
+                consecutive_failures: 2
+            }
+        );
+        assert_eq!(tracker.observe(false, true), Verdict::Healthy);
+        assert_eq!(
+            tracker.observe(false, false),
+            Verdict::Degraded {
+                consecutive_failures: 1
+            }
+        );
+    }
+
+    #[test]
+    fn tracker_declares_dead_after_sustained_failures() {
+        let mut tracker = HealthTracker::new(3);
+
+        tracker.observe(false, false);
+        tracker.observe(false, false);
+        assert_eq!(tracker.observe(false, false), Verdict::Dead);
+    }
+
+    #[test]
+    fn tracker_reacts_to_crash_immediately() {
+        let mut tracker = HealthTracker::new(3);
+
+        assert_eq!(tracker.observe(false, true), Verdict::Healthy);
+        assert_eq!(tracker.observe(true, false), Verdict::Dead);
+    }
+
+    #[test]
+    fn connection_is_offline_only_after_the_grace_period() {
+        let grace = Duration::from_secs(3);
+        let start = Instant::now();
+        let mut tracker = ConnectionTracker::new(grace);
+
+        assert_eq!(tracker.observe(false, start), None);
+        assert_eq!(tracker.observe(false, start + Duration::from_secs(2)), None);
+        // A blip that recovers within the grace period is never reported.
+        assert_eq!(tracker.observe(true, start + Duration::from_secs(2)), None);
+
+        let failing = start + Duration::from_secs(5);
+        assert_eq!(tracker.observe(false, failing), None);
+        assert_eq!(
+            tracker.observe(false, failing + grace),
+            Some(ConnectionChange::Lost)
+        );
+        assert_eq!(tracker.observe(false, failing + grace * 2), None);
+    }
+
+    #[test]
+    fn connection_recovery_is_reported_immediately() {
+        let start = Instant::now();
+        let mut tracker = ConnectionTracker::new(Duration::ZERO);
+
+        assert_eq!(tracker.observe(false, start), Some(ConnectionChange::Lost));
+        assert_eq!(
+            tracker.observe(true, start),
+            Some(ConnectionChange::Restored)
+        );
+        assert_eq!(tracker.observe(true, start), None);
+    }
+
+    #[test]
+    fn settings_report_whether_the_environment_set_them() {
+        let name = "SUWAYOMI_TEST_SETTING_SOURCE";
+        let setting = |name| Setting::from_env(name, 7, |n| (n > 0).then_some(n));
+
+        env::set_var(name, " 12 ");
+        assert_eq!(
+            setting(name),
+            Setting {
+                value: 12,
+                source: SettingSource::Env
+            }
+        );
+        env::set_var(name, "0");
+        assert_eq!(setting(name).source, SettingSource::Default);
+        env::remove_var(name);
+        assert_eq!(setting(name).value, 7);
+    }
+
+    #[test]
+    fn wake_cuts_a_sleep_short() {
+        let started = Instant::now();
+        let sleeper = thread::spawn(|| sleep_unless_woken(Duration::from_secs(30)));
+
+        thread::sleep(Duration::from_millis(100));
+        wake();
+        sleeper.join().expect("sleeper");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn saved_policies_round_trip_and_yield_to_the_environment() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("launcher").join("launcher-state.json");
+        assert_eq!(load_policy(&path), None);
+
+        let policy = ReconnectPolicy {
+            offline_grace_secs: 10,
+            probe_interval_secs: 2,
+            failure_threshold: 5,
+            max_restart_attempts: 3,
+        };
+        save_policy(&path, &policy).expect("save");
+        assert_eq!(load_policy(&path), Some(policy));
+
+        // Knobs missing from an older file keep their defaults.
+        fs::write(&path, r#"{"reconnectPolicy": {"probeIntervalSecs": 9}}"#).expect("write");
+        let partial = load_policy(&path).expect("partial policy");
+        assert_eq!(partial.probe_interval_secs, 9);
+        assert_eq!(partial.failure_threshold, DEFAULT_FAILURE_THRESHOLD);
+        fs::write(&path, "not json").expect("write");
+        assert_eq!(load_policy(&path), None);
+
+        let name = "SUWAYOMI_TEST_SAVED_SETTING";
+        let setting = |name| Setting::from_env(name, 7, |n| (n > 0).then_some(n)).or_saved(Some(4));
+        assert_eq!(
+            setting(name),
+            Setting {
+                value: 4,
+                source: SettingSource::Saved
+            }
+        );
+        env::set_var(name, "12");
+        assert_eq!(setting(name).value, 12);
+        env::remove_var(name);
+    }
+
+    #[test]
+    fn restarts_that_keep_failing_back_off_and_are_given_up_on() {
+        let policy = SupervisorPolicy {
+            interval: Duration::from_secs(5),
+            failure_threshold: 1,
+            max_restart_attempts: 3,
+        };
+        let base_url = "http://127.0.0.1:4567";
+        let never_starts = || Err(LauncherError::SpawnServer("no java".to_string()));
+        let started = Instant::now();
+        let mut restarts = Restarts::default();
+
+        assert!(restarts
+            .attempt(base_url, policy.interval, started, never_starts)
+            .is_err());
+        assert!(restarts.is_retrying());
+        assert_eq!(restarts.due(started + Duration::from_secs(4)), None);
+        let retry = started + Duration::from_secs(5);
+        assert_eq!(restarts.due(retry), Some(base_url));
+
+        assert!(restarts
+            .attempt(base_url, policy.interval, retry, never_starts)
+            .is_err());
+        assert_eq!(restarts.due(retry + Duration::from_secs(9)), None);
+        let retry = retry + Duration::from_secs(10);
+        assert_eq!(restarts.due(retry), Some(base_url));
+        assert!(!restarts.exhausted(&policy));
+
+        assert!(restarts
+            .attempt(base_url, policy.interval, retry, never_starts)
+            .is_err());
+        assert!(restarts.exhausted(&policy));
+        assert_eq!(restarts.give_up(), 3);
+        assert!(!restarts.is_retrying());
+
+        let mut restarts = Restarts::default();
+        let starts = || {
+            Ok(LauncherBootstrap {
+                base_url: base_url.to_string(),
+            })
+        };
+        assert!(restarts
+            .attempt(base_url, policy.interval, started, starts)
+            .is_ok());
+        assert!(!restarts.is_retrying());
+    }
+
+    #[test]
+    fn default_policy_is_conservative() {
+        let policy = SupervisorPolicy::default();
+
+        assert!(policy.interval * policy.failure_threshold >= Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::launcher;
+use crate::{migrations, updates};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Suwayomi-Server v2 is built for Java 21; older runtimes may start but break later.
+const MIN_JAVA_MAJOR: u32 = 21;
+/// A runtime that takes longer than this to print its version is not going to run the
+/// server either.
+const JAVA_VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Below this the database and downloads are likely to fail to write.
+const MIN_FREE_BYTES: u64 = 200 * 1024 * 1024;
+/// Below this a library update or a few downloaded chapters can fill the disk.
+const LOW_FREE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One line of the system check: what was checked, how it went and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// Runs every check, in the order they matter for starting a server. `webview` is the
+/// installed webview version, or why it could not be found.
+pub fn run(resource_dir: Option<PathBuf>, webview: Result<String, String>) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+
+    match launcher::runtime_paths(resource_dir) {
+        Ok((java, jar)) => {
+            results.push(check_java(&java));
+            results.push(check_jar(&jar));
+        }
+        // The error names whichever of the two is missing.
+        Err(err) => results.push(CheckResult::new(
+            "runtime",
+            CheckStatus::Fail,
+            err.to_string(),
+        )),
+    }
+
+    results.push(check_port());
+    let root_dir = launcher::server_root_dir();
+    results.push(check_data_dir(&root_dir));
+    results.push(check_disk_space(&root_dir));
+    results.push(match webview {
+        Ok(version) => CheckResult::new("webview", CheckStatus::Pass, version),
+        Err(err) => CheckResult::new("webview", CheckStatus::Fail, err),
+    });
+
+    results
+}
+
+fn check_java(java: &Path) -> CheckResult {
+    let mut command = Command::new(java);
+    command
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(launcher::CREATE_NO_WINDOW);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            return CheckResult::new(
+                "java",
+                CheckStatus::Fail,
+                format!("cannot run {}: {err}", java.display()),
+            )
+        }
+    };
+    if !launcher::wait_for_exit(&mut child, JAVA_VERSION_TIMEOUT) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return CheckResult::new(
+            "java",
+            CheckStatus::Fail,
+            format!(
+                "{} did not report a version within {} seconds",
+                java.display(),
+                JAVA_VERSION_TIMEOUT.as_secs()
+            ),
+        );
+    }
+
+    let output = match child.wait_with_output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            return CheckResult::new(
+                "java",
+                CheckStatus::Fail,
+                format!("{} exited with {}", java.display(), output.status),
+            )
+        }
+        Err(err) => {
+            return CheckResult::new(
+                "java",
+                CheckStatus::Fail,
+                format!("cannot read the output of {}: {err}", java.display()),
+            )
+        }
+    };
+
+    // `-version` prints to stderr.
+    let banner = String::from_utf8_lossy(&output.stderr);
+    match java_version(&banner) {
+        Some((version, major)) if major >= MIN_JAVA_MAJOR => {
+            CheckResult::new("java", CheckStatus::Pass, format!("Java {version}"))
+        }
+        Some((version, _)) => CheckResult::new(
+            "java",
+            CheckStatus::Warn,
+            format!("Java {version} is older than the Java {MIN_JAVA_MAJOR} the server needs"),
+        ),
+        None => CheckResult::new(
+            "java",
+            CheckStatus::Warn,
+            format!("{} did not report a version", java.display()),
+        ),
+    }
+}
+
+/// The quoted version from a `java -version` banner and its major version, which for
+/// Java 8 and older is the part after `1.`.
+fn java_version(banner: &str) -> Option<(String, u32)> {
+    let version = banner.lines().next()?.split('"').nth(1)?;
+    let mut parts = version.split(['.', '_', '-', '+']);
+    let major = match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok()?,
+        major => major,
+    };
+    Some((version.to_string(), major))
+}
+
+fn check_jar(jar: &Path) -> CheckResult {
+    let sha256 = match updates::sha256_file(jar) {
+        Ok(sha256) => sha256,
+        Err(err) => {
+            return CheckResult::new(
+                "jar",
+                CheckStatus::Fail,
+                format!("cannot read {}: {err}", jar.display()),
+            )
+        }
+    };
+
+    match migrations::jar_version(jar) {
+        Some(version) => CheckResult::new(
+            "jar",
+            CheckStatus::Pass,
+            format!("Suwayomi-Server {version} (SHA-256 {sha256})"),
+        ),
+        None => CheckResult::new(
+            "jar",
+            CheckStatus::Warn,
+            format!(
+                "{} has no server version in its manifest and may be damaged",
+                jar.display()
+            ),
+        ),
+    }
+}
+
+/// A taken port is fine when it is the server the launcher would use anyway.
+fn check_port() -> CheckResult {
+    let config = launcher::config_report();
+    if launcher::is_port_free(&config.ip, config.port) {
+        return CheckResult::new(
+            "port",
+            CheckStatus::Pass,
+            format!("{} is free", config.port),
+        );
+    }
+    if launcher::is_server_healthy(&config.base_url) {
+        return CheckResult::new(
+            "port",
+            CheckStatus::Pass,
+            format!("a server is already running at {}", config.base_url),
+        );
+    }
+
+    CheckResult::new(
+        "port",
+        CheckStatus::Fail,
+        format!(
+            "port {} is in use by another program; change `server.port` in {}",
+            config.port,
+            config
+                .path
+                .as_deref()
+                .map_or("server.conf".to_string(), |path| path.display().to_string())
+        ),
+    )
+}
+
+fn check_data_dir(root_dir: &Path) -> CheckResult {
+    let shown = root_dir.display();
+    match fs::metadata(root_dir) {
+        Ok(metadata) if !metadata.is_dir() => {
+            CheckResult::new("dataDir", CheckStatus::Fail, format!("{shown} is a file"))
+        }
+        Ok(_) => match launcher::probe_writable(root_dir) {
+            Ok(()) => {
+                CheckResult::new("dataDir", CheckStatus::Pass, format!("{shown} is writable"))
+            }
+            Err(err) => CheckResult::new(
+                "dataDir",
+                CheckStatus::Fail,
+                format!("cannot write to {shown}: {err}"),
+            ),
+        },
+        Err(_) => CheckResult::new(
+            "dataDir",
+            CheckStatus::Pass,
+            format!("{shown} will be created when the server first starts"),
+        ),
+    }
+}
+
+fn check_disk_space(root_dir: &Path) -> CheckResult {
+    // The data dir may not exist yet; the drive it will be created on is what counts.
+    let existing = root_dir.ancestors().find(|dir| dir.is_dir());
+    let Some(free) = existing.and_then(free_bytes) else {
+        return CheckResult::new(
+            "diskSpace",
+            CheckStatus::Warn,
+            format!(
+                "cannot tell how much space is free for {}",
+                root_dir.display()
+            ),
+        );
+    };
+
+    CheckResult::new(
+        "diskSpace",
+        space_status(free),
+        format!("{} free", format_bytes(free)),
+    )
+}
+
+fn space_status(free: u64) -> CheckStatus {
+    if free < MIN_FREE_BYTES {
+        CheckStatus::Fail
+    } else if free < LOW_FREE_BYTES {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MIB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GIB {
+        format!("{:.1} GiB", bytes / GIB)
+    } else {
+        format!("{:.0} MiB", bytes / MIB)
+    }
+}
+
+// The field types differ between platforms.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn free_bytes(dir: &Path) -> Option<u64> {
+    let stats = nix::sys::statvfs::statvfs(dir).ok()?;
+    Some(stats.blocks_available() as u64 * stats.fragment_size() as u64)
+}
+
+#[cfg(windows)]
+fn free_bytes(dir: &Path) -> Option<u64> {
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let mut free = 0;
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(dir.as_os_str()), Some(&mut free), None, None) }
+        .ok()?;
+    Some(free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_versions_are_read_from_the_banner() {
+        assert_eq!(
+            java_version("openjdk version \"21.0.2\" 2024-01-16\nOpenJDK Runtime Environment"),
+            Some(("21.0.2".to_string(), 21))
+        );
+        assert_eq!(
+            java_version("java version \"1.8.0_392\"\nJava(TM) SE Runtime Environment"),
+            Some(("1.8.0_392".to_string(), 8))
+        );
+        assert_eq!(
+            java_version("openjdk version \"22-ea\" 2024-03-19"),
+            Some(("22-ea".to_string(), 22))
+        );
+        assert_eq!(java_version("Error: could not find libjava.so"), None);
+    }
+
+    #[test]
+    fn data_dir_and_disk_space_checks() {
+        let temp = tempfile::tempdir().expect("temp dir");
+        assert_eq!(check_data_dir(temp.path()).status, CheckStatus::Pass);
+        assert_eq!(
+            check_data_dir(&temp.path().join("new")).status,
+            CheckStatus::Pass
+        );
+        let file = temp.path().join("file");
+        fs::write(&file, b"").expect("write file");
+        assert_eq!(check_data_dir(&file).status, CheckStatus::Fail);
+
+        // Measured on the nearest directory that exists.
+        let space = check_disk_space(&temp.path().join("not").join("yet"));
+        assert!(space.message.ends_with(" free"), "{}", space.message);
+        assert_eq!(space_status(100 * 1024 * 1024), CheckStatus::Fail);
+        assert_eq!(space_status(LOW_FREE_BYTES - 1), CheckStatus::Warn);
+        assert_eq!(space_status(LOW_FREE_BYTES), CheckStatus::Pass);
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.5 GiB");
+        assert_eq!(format_bytes(300 * 1024 * 1024), "300 MiB");
+    }
+
+    #[test]
+    fn missing_or_broken_jars_do_not_pass() {
+        let temp = tempfile::tempdir().expect("temp dir");
+        assert_eq!(
+            check_jar(&temp.path().join("Suwayomi-Server.jar")).status,
+            CheckStatus::Fail
+        );
+        let jar = temp.path().join("broken.jar");
+        fs::write(&jar, b"not a jar").expect("write jar");
+        assert_eq!(check_jar(&jar).status, CheckStatus::Warn);
+    }
+}
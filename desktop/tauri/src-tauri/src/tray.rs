@@ -0,0 +1,106 @@
+use crate::{launcher, library};
+use std::thread;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{App, AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const TRAY_ID: &str = "main";
+const SHOW_WINDOW_ID: &str = "show-window";
+const COPY_URL_ID: &str = "copy-server-url";
+const UPDATE_LIBRARY_ID: &str = "update-library";
+const QUIT_ID: &str = "quit";
+
+/// Menu entry showing the effective server URL; kept so it can be updated in place.
+struct TrayState {
+    url_item: MenuItem<Wry>,
+}
+
+pub fn create(app: &App) -> tauri::Result<()> {
+    let url_item = MenuItem::with_id(app, "server-url", "Server: starting…", false, None::<&str>)?;
+    let show_item = MenuItem::with_id(app, SHOW_WINDOW_ID, "Show Suwayomi", true, None::<&str>)?;
+    let copy_item = MenuItem::with_id(app, COPY_URL_ID, "Copy server URL", true, None::<&str>)?;
+    let update_item =
+        MenuItem::with_id(app, UPDATE_LIBRARY_ID, "Update library", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, QUIT_ID, "Quit", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &url_item,
+            &show_item,
+            &copy_item,
+            &update_item,
+            &PredefinedMenuItem::separator(app)?,
+            &quit_item,
+        ],
+    )?;
+
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip("Suwayomi")
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            SHOW_WINDOW_ID => show_main_window(app),
+            COPY_URL_ID => {
+                if let Err(err) = copy_server_url(app) {
+                    eprintln!("failed to copy server URL: {err}");
+                }
+            }
+            UPDATE_LIBRARY_ID => update_library(app),
+            QUIT_ID => app.exit(0),
+            _ => {}
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayState { url_item });
+    Ok(())
+}
+
+/// Whether [`create`] managed to add the tray icon.
+pub fn exists(app: &AppHandle) -> bool {
+    app.tray_by_id(TRAY_ID).is_some()
+}
+
+/// Shows `base_url` in the tray menu and tooltip.
+pub fn show_base_url(app: &AppHandle, base_url: &str) {
+    if let Some(state) = app.try_state::<TrayState>() {
+        let _ = state.url_item.set_text(format!("Server: {base_url}"));
+    }
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(format!("Suwayomi — {base_url}")));
+    }
+}
+
+/// Puts the URL the app is currently using on the clipboard and returns it.
+pub fn copy_server_url(app: &AppHandle) -> Result<String, String> {
+    let base_url = launcher::active_base_url().ok_or("not connected to a server")?;
+    app.clipboard()
+        .write_text(base_url.clone())
+        .map_err(|err| err.to_string())?;
+    Ok(base_url)
+}
+
+/// Runs a library update in the background, reporting progress like the `update_library`
+/// command does.
+fn update_library(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let result = library::update(|progress| {
+            let _ = app.emit("library-update-progress", progress);
+        });
+        if let Err(err) = result {
+            eprintln!("library update failed: {err}");
+        }
+    });
+}
+
+/// Brings the main window back after it was hidden to the tray.
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
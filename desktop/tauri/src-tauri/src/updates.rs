@@ -0,0 +1,312 @@
+use crate::http;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+const RELEASES_API: &str = "https://api.github.com/repos/Suwayomi/Suwayomi-Server/releases/tags";
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("could not fetch release {0}")]
+    ReleaseNotFound(String),
+    #[error("release {0} has no server jar")]
+    NoJarAsset(String),
+    #[error("release {version} publishes no checksum for {asset}")]
+    MissingChecksum { version: String, asset: String },
+    #[error("failed to download {asset}: {reason}")]
+    Download { asset: String, reason: String },
+    #[error("checksum mismatch for {asset}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("cannot write next to {}: {source}", .path.display())]
+    NotWritable { path: PathBuf, source: io::Error },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReleaseAsset {
+    name: String,
+    url: String,
+    sha256: String,
+}
+
+/// Downloads the server jar of release `version`, verifies it against the SHA-256 digest
+/// GitHub publishes for the asset and stages it as `<jar>.new`. Nothing is replaced here;
+/// [`apply_staged`] swaps the jar in on the next server start. Returns the staged path.
+pub fn download_and_stage(jar: &Path, version: &str) -> Result<PathBuf, UpdateError> {
+    let tag = release_tag(version);
+    let release = http::get_anonymous(&format!("{RELEASES_API}/{tag}"), RELEASE_TIMEOUT)
+        .filter(|response| response.status == 200)
+        .ok_or_else(|| UpdateError::ReleaseNotFound(tag.clone()))?;
+    let asset = jar_asset(&tag, &release.body)?;
+
+    let staged = stage_with(jar, &asset, |out| {
+        http::download(&asset.url, DOWNLOAD_TIMEOUT, out)
+            .map(drop)
+            .map_err(|reason| UpdateError::Download {
+                asset: asset.name.clone(),
+                reason,
+            })
+    })?;
+
+    eprintln!("staged server {tag} at {}", staged.display());
+    Ok(staged)
+}
+
+/// Replaces `jar` with a staged update if one exists and still matches its recorded
+/// checksum. A staged file that fails verification is discarded and the current jar kept.
+pub fn apply_staged(jar: &Path) {
+    let staged = sibling(jar, ".new");
+    let checksum_file = sibling(jar, ".new.sha256");
+    if !staged.exists() {
+        return;
+    }
+
+    let expected = fs::read_to_string(&checksum_file).unwrap_or_default();
+    let verified = sha256_file(&staged)
+        .is_ok_and(|actual| !expected.trim().is_empty() && actual == expected.trim());
+
+    if verified {
+        // Same directory, so the rename is atomic and the old jar is never half-replaced.
+        match fs::rename(&staged, jar) {
+            Ok(()) => eprintln!("applied staged server update to {}", jar.display()),
+            Err(err) => eprintln!("failed to apply staged server update: {err}"),
+        }
+    } else {
+        eprintln!("discarding staged server update that failed verification");
+        let _ = fs::remove_file(&staged);
+    }
+
+    let _ = fs::remove_file(&checksum_file);
+}
+
+fn release_tag(version: &str) -> String {
+    let version = version.trim();
+    if version.starts_with('v') {
+        version.to_string()
+    } else {
+        format!("v{version}")
+    }
+}
+
+fn jar_asset(tag: &str, release_json: &[u8]) -> Result<ReleaseAsset, UpdateError> {
+    let release: Value = serde_json::from_slice(release_json)
+        .map_err(|_| UpdateError::ReleaseNotFound(tag.to_string()))?;
+    let asset = release
+        .get("assets")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .find(|asset| {
+            asset
+                .get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|name| name.starts_with("Suwayomi-Server") && name.ends_with(".jar"))
+        })
+        .ok_or_else(|| UpdateError::NoJarAsset(tag.to_string()))?;
+
+    let text = |key: &str| asset.get(key).and_then(Value::as_str).unwrap_or_default();
+    let name = text("name").to_string();
+    let sha256 = text("digest")
+        .strip_prefix("sha256:")
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| UpdateError::MissingChecksum {
+            version: tag.to_string(),
+            asset: name.clone(),
+        })?;
+
+    Ok(ReleaseAsset {
+        url: text("browser_download_url").to_string(),
+        name,
+        sha256,
+    })
+}
+
+/// Writes the asset through `fetch` into `<jar>.new.part`, verifies it and only then
+/// moves it to `<jar>.new` with its checksum alongside.
+fn stage_with(
+    jar: &Path,
+    asset: &ReleaseAsset,
+    fetch: impl FnOnce(&mut HashingWriter<File>) -> Result<(), UpdateError>,
+) -> Result<PathBuf, UpdateError> {
+    let partial = sibling(jar, ".new.part");
+    let staged = sibling(jar, ".new");
+    let not_writable = |source| UpdateError::NotWritable {
+        path: jar.to_path_buf(),
+        source,
+    };
+
+    let mut out = HashingWriter::new(File::create(&partial).map_err(not_writable)?);
+    let fetched = fetch(&mut out).and_then(|()| out.flush().map_err(not_writable));
+    let actual = out.finish();
+
+    if let Err(err) = fetched {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+
+    if actual != asset.sha256 {
+        let _ = fs::remove_file(&partial);
+        return Err(UpdateError::ChecksumMismatch {
+            asset: asset.name.clone(),
+            expected: asset.sha256.clone(),
+            actual,
+        });
+    }
+
+    fs::write(sibling(jar, ".new.sha256"), &actual).map_err(not_writable)?;
+    fs::rename(&partial, &staged).map_err(not_writable)?;
+    Ok(staged)
+}
+
+fn sibling(jar: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(jar.file_name().unwrap_or_default());
+    name.push(suffix);
+    jar.with_file_name(name)
+}
+
+pub(crate) fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = HashingWriter::new(io::sink());
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+/// Hashes everything written through it, so the download is verified without a second read.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAR_SHA256: &str = "4f8c9b4b8fc3a6fc5bbd8d5bdc4a8bb6ba76e0f1fce2b48aa868fa1f7dbe0a59";
+
+    fn asset(sha256: &str) -> ReleaseAsset {
+        ReleaseAsset {
+            name: "Suwayomi-Server-v2.0.1727.jar".to_string(),
+            url: "https://example.invalid/server.jar".to_string(),
+            sha256: sha256.to_string(),
+        }
+    }
+
+    fn sha256_of(data: &[u8]) -> String {
+        let mut hasher = HashingWriter::new(io::sink());
+        hasher.write_all(data).expect("hash");
+        hasher.finish()
+    }
+
+    #[test]
+    fn picks_the_server_jar_and_its_digest() {
+        let release = format!(
+            r#"{{"assets":[
+                {{"name":"Suwayomi-Server-v2.0.1727-windows-x64.zip","digest":"sha256:00"}},
+                {{"name":"Suwayomi-Server-v2.0.1727.jar","digest":"sha256:{}",
+                  "browser_download_url":"https://example.invalid/server.jar"}}]}}"#,
+            JAR_SHA256.to_ascii_uppercase()
+        );
+
+        assert_eq!(
+            jar_asset("v2.0.1727", release.as_bytes()).expect("asset"),
+            asset(JAR_SHA256)
+        );
+        assert!(matches!(
+            jar_asset(
+                "v2.0.1727",
+                br#"{"assets":[{"name":"Suwayomi-Server-v2.0.1727.jar"}]}"#
+            ),
+            Err(UpdateError::MissingChecksum { .. })
+        ));
+        assert_eq!(release_tag("2.0.1727"), "v2.0.1727");
+    }
+
+    #[test]
+    fn staged_jar_replaces_the_current_one_only_when_verified() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let jar = dir.path().join("Suwayomi-Server.jar");
+        fs::write(&jar, b"old").expect("write jar");
+
+        let staged = stage_with(&jar, &asset(&sha256_of(b"new")), |out| {
+            out.write_all(b"new")
+                .map_err(|source| UpdateError::NotWritable {
+                    path: PathBuf::new(),
+                    source,
+                })
+        })
+        .expect("stage");
+        assert_eq!(fs::read(&jar).expect("read jar"), b"old");
+
+        fs::write(&staged, b"tampered").expect("tamper");
+        apply_staged(&jar);
+        assert_eq!(fs::read(&jar).expect("read jar"), b"old");
+        assert!(!staged.exists());
+
+        stage_with(&jar, &asset(&sha256_of(b"new")), |out| {
+            out.write_all(b"new")
+                .map_err(|source| UpdateError::NotWritable {
+                    path: PathBuf::new(),
+                    source,
+                })
+        })
+        .expect("stage");
+        apply_staged(&jar);
+        assert_eq!(fs::read(&jar).expect("read jar"), b"new");
+    }
+
+    #[test]
+    fn download_with_wrong_checksum_is_not_staged() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let jar = dir.path().join("Suwayomi-Server.jar");
+
+        let err = stage_with(&jar, &asset(JAR_SHA256), |out| {
+            out.write_all(b"corrupt")
+                .map_err(|source| UpdateError::NotWritable {
+                    path: PathBuf::new(),
+                    source,
+                })
+        })
+        .unwrap_err();
+
+        assert!(matches!(err, UpdateError::ChecksumMismatch { .. }));
+        assert_eq!(fs::read_dir(dir.path()).expect("read dir").count(), 0);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::launcher;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Download folders nest source/manga/chapter/page; anything far deeper is a loop or junk.
+const MAX_DEPTH: usize = 12;
+/// A huge library on a network drive must not leave the UI waiting indefinitely.
+const MAX_WALK_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    pub root_dir: String,
+    /// Everything under the root dir, downloads included when they are stored there.
+    pub total_bytes: u64,
+    pub downloads_bytes: u64,
+    /// Set when the downloads folder is outside the root dir, so not part of the total.
+    pub downloads_dir: Option<String>,
+    pub database_bytes: u64,
+    pub thumbnails_bytes: u64,
+    /// `false` when the walk hit its depth or time limit, making the sizes lower bounds.
+    pub complete: bool,
+}
+
+/// Sizes of the server's data directory and the parts of it the launcher knows about.
+pub fn data_dir_usage() -> DiskUsage {
+    usage_of(
+        &launcher::server_root_dir(),
+        &launcher::server_downloads_dir(),
+        MAX_WALK_TIME,
+    )
+}
+
+fn usage_of(root_dir: &Path, downloads_dir: &Path, budget: Duration) -> DiskUsage {
+    let mut walk = Walk {
+        deadline: Instant::now() + budget,
+        complete: true,
+        downloads_dir,
+        thumbnails_dir: root_dir.join("thumbnails"),
+        sizes: Sizes::default(),
+    };
+
+    // One walk covers all of the root dir; only downloads kept elsewhere need another.
+    let downloads_outside = !downloads_dir.starts_with(root_dir);
+    walk.add(root_dir, Part::Other, true);
+    if downloads_outside {
+        walk.add(downloads_dir, Part::Downloads, false);
+    }
+    let database_bytes = launcher::DATABASE_FILES
+        .iter()
+        .filter_map(|name| fs::metadata(root_dir.join(name)).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    DiskUsage {
+        root_dir: root_dir.display().to_string(),
+        total_bytes: walk.sizes.total,
+        downloads_bytes: walk.sizes.downloads,
+        downloads_dir: downloads_outside.then(|| downloads_dir.display().to_string()),
+        database_bytes,
+        thumbnails_bytes: walk.sizes.thumbnails,
+        complete: walk.complete,
+    }
+}
+
+/// The part of the data dir a file is counted in besides the total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Other,
+    Downloads,
+    Thumbnails,
+}
+
+#[derive(Debug, Default)]
+struct Sizes {
+    total: u64,
+    downloads: u64,
+    thumbnails: u64,
+}
+
+struct Walk<'a> {
+    deadline: Instant,
+    complete: bool,
+    downloads_dir: &'a Path,
+    thumbnails_dir: PathBuf,
+    sizes: Sizes,
+}
+
+impl Walk<'_> {
+    /// Adds up the files under `dir` as `part`, and into the total with `in_total`.
+    /// Reaching the downloads or thumbnails dir switches to that part. Symlinks are not
+    /// followed, so a link back up the tree cannot be counted twice; unreadable entries are
+    /// skipped.
+    fn add(&mut self, dir: &Path, part: Part, in_total: bool) {
+        let mut pending = vec![(dir.to_path_buf(), 0, part)];
+
+        while let Some((dir, depth, part)) = pending.pop() {
+            if Instant::now() >= self.deadline {
+                self.complete = false;
+                break;
+            }
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if file_type.is_dir() {
+                    if depth < MAX_DEPTH {
+                        let path = entry.path();
+                        let part = self.part_of(&path).unwrap_or(part);
+                        pending.push((path, depth + 1, part));
+                    } else {
+                        self.complete = false;
+                    }
+                } else if file_type.is_file() {
+                    let len = entry.metadata().map_or(0, |metadata| metadata.len());
+                    if in_total {
+                        self.sizes.total += len;
+                    }
+                    match part {
+                        Part::Downloads => self.sizes.downloads += len,
+                        Part::Thumbnails => self.sizes.thumbnails += len,
+                        Part::Other => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn part_of(&self, dir: &Path) -> Option<Part> {
+        if dir == self.downloads_dir {
+            Some(Part::Downloads)
+        } else if dir == self.thumbnails_dir {
+            Some(Part::Thumbnails)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_is_broken_down_into_the_known_parts() {
+        let root = tempfile::tempdir().expect("temp dir");
+        let chapter = root.path().join("downloads").join("source").join("manga");
+        fs::create_dir_all(&chapter).expect("downloads");
+        fs::write(chapter.join("001.png"), [0; 300]).expect("page");
+        fs::create_dir(root.path().join("thumbnails")).expect("thumbnails");
+        fs::write(root.path().join("thumbnails").join("1"), [0; 20]).expect("thumbnail");
+        fs::write(root.path().join("database.mv.db"), [0; 100]).expect("database");
+
+        let usage = usage_of(
+            root.path(),
+            &root.path().join("downloads"),
+            Duration::from_secs(10),
+        );
+        assert_eq!(usage.total_bytes, 420);
+        assert_eq!(usage.downloads_bytes, 300);
+        assert_eq!(usage.downloads_dir, None);
+        assert_eq!(usage.thumbnails_bytes, 20);
+        assert_eq!(usage.database_bytes, 100);
+        assert!(usage.complete);
+    }
+
+    #[test]
+    fn walks_past_their_limits_are_marked_incomplete() {
+        let root = tempfile::tempdir().expect("temp dir");
+        let mut deep = root.path().to_path_buf();
+        for level in 0..=MAX_DEPTH {
+            deep.push(level.to_string());
+        }
+        fs::create_dir_all(&deep).expect("deep dirs");
+        fs::write(deep.join("page.png"), [0; 10]).expect("page");
+        let elsewhere = tempfile::tempdir().expect("downloads dir");
+
+        let deep_usage = usage_of(root.path(), elsewhere.path(), Duration::from_secs(10));
+        assert!(!deep_usage.complete);
+        assert_eq!(deep_usage.total_bytes, 0);
+        assert!(deep_usage.downloads_dir.is_some());
+
+        assert!(!usage_of(root.path(), elsewhere.path(), Duration::ZERO).complete);
+    }
+}